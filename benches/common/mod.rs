@@ -0,0 +1,30 @@
+use criterion::Criterion;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Criterion settings short enough to run on every CI build.
+///
+/// Save a baseline with `cargo bench --features bench --bench '*' -- --save-baseline main` on
+/// the base branch, then run `scripts/bench-gate.sh main 0.10` on the change to fail when a
+/// benchmark is more than 10% slower. Differences below the noise threshold are reported as
+/// unchanged.
+pub fn ci() -> Criterion {
+    Criterion::default()
+        .sample_size(20)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(2))
+        .noise_threshold(0.05)
+}
+
+/// A document of `tables` tables with 20 commented keys each.
+pub fn document(tables: usize) -> String {
+    let mut src = String::new();
+    for t in 0..tables {
+        writeln!(src, "[service_{t}]").unwrap();
+        for k in 0..20 {
+            writeln!(src, "key_{k} = \"value {t} {k}\" # comment").unwrap();
+        }
+        writeln!(src).unwrap();
+    }
+    src
+}
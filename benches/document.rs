@@ -0,0 +1,114 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::hint::black_box;
+use std::io::Write;
+use toml_edit::{Array, DocumentMut, Value};
+use tomldb::cache::DocCache;
+use tomldb::ops::{dedupe_array, set_field, sort_array};
+use tomldb::KeyPath;
+
+mod common;
+
+fn parse(c: &mut Criterion) {
+    let src = common::document(1000);
+    c.bench_function("parse_1000_tables", |b| {
+        b.iter(|| black_box(&src).parse::<DocumentMut>().unwrap())
+    });
+}
+
+fn edit(c: &mut Criterion) {
+    let doc: DocumentMut = common::document(1000).parse().unwrap();
+    let one: KeyPath = "service_500.key_10".parse().unwrap();
+    let batch: Vec<KeyPath> = (0..1000)
+        .map(|i| format!("service_{i}.key_{}", i % 20).parse().unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("edit");
+    group.bench_function("single_key", |b| {
+        b.iter_batched_ref(
+            || doc.clone(),
+            |doc| {
+                set_field(doc, &one, Value::from(1)).unwrap();
+                black_box(doc.to_string())
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("batch_1000_keys", |b| {
+        b.iter_batched_ref(
+            || doc.clone(),
+            |doc| {
+                for path in &batch {
+                    set_field(doc, path, Value::from(1)).unwrap();
+                }
+                black_box(doc.to_string())
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn large_array(c: &mut Criterion) {
+    let values: Array = (0..10_000i64).map(|i| (i * 7919) % 1000).collect();
+
+    let mut group = c.benchmark_group("large_array");
+    group.bench_function("append_10000", |b| {
+        b.iter(|| {
+            let mut array = Array::new();
+            for value in values.iter() {
+                array.push(value.clone());
+            }
+            black_box(array.to_string())
+        })
+    });
+    group.bench_function("sort_10000", |b| {
+        b.iter_batched_ref(|| values.clone(), sort_array, BatchSize::LargeInput)
+    });
+    group.bench_function("dedupe_10000", |b| {
+        b.iter_batched_ref(|| values.clone(), dedupe_array, BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+fn cache(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("tomldb-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("cache.toml");
+    std::fs::write(&path, common::document(1000)).unwrap();
+
+    let mut group = c.benchmark_group("doc_cache");
+    let cache = DocCache::new();
+    group.bench_function("hit", |b| b.iter(|| cache.get(black_box(&path)).unwrap()));
+    group.bench_function("miss", |b| {
+        b.iter(|| {
+            cache.invalidate(&path);
+            cache.get(black_box(&path)).unwrap()
+        })
+    });
+    group.finish();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// The I/O a commit has to pay for: writing the rendered document and syncing it to disk.
+fn write_fsync(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("tomldb-bench-fsync-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.toml");
+    let doc: DocumentMut = common::document(1000).parse().unwrap();
+
+    c.bench_function("write_fsync_1000_tables", |b| {
+        b.iter(|| {
+            let mut file = std::fs::File::create(&path).unwrap();
+            file.write_all(doc.to_string().as_bytes()).unwrap();
+            file.sync_all().unwrap();
+        })
+    });
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group! {
+    name = benches;
+    config = common::ci();
+    targets = parse, edit, large_array, cache, write_fsync
+}
+criterion_main!(benches);
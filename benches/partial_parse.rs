@@ -0,0 +1,42 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use toml_edit::DocumentMut;
+use tomldb::scan::read_table;
+use tomldb::walk::get;
+use tomldb::KeyPath;
+
+mod common;
+
+fn view_one_table(c: &mut Criterion) {
+    let src = common::document(1000);
+    let path: KeyPath = "service_500".parse().unwrap();
+    let mut group = c.benchmark_group("view_one_table");
+    group.bench_function("full_parse", |b| {
+        b.iter(|| {
+            let doc: DocumentMut = black_box(&src).parse().unwrap();
+            black_box(get(&doc, &path).is_some());
+        })
+    });
+    group.bench_function("read_table", |b| {
+        b.iter(|| black_box(read_table(black_box(&src), &path).unwrap()))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "bench")]
+fn lex_sections(c: &mut Criterion) {
+    let src = common::document(1000);
+    c.bench_function("lex_sections", |b| {
+        b.iter(|| black_box(tomldb::scan::bench::sections(black_box(&src))))
+    });
+}
+
+#[cfg(not(feature = "bench"))]
+fn lex_sections(_: &mut Criterion) {}
+
+criterion_group! {
+    name = benches;
+    config = common::ci();
+    targets = view_one_table, lex_sections
+}
+criterion_main!(benches);
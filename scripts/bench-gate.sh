@@ -0,0 +1,39 @@
+#!/bin/sh
+# Fails when a benchmark is slower than a saved criterion baseline by more than a threshold.
+#
+# Usage: scripts/bench-gate.sh [baseline] [threshold] [filter]
+#
+# Save the baseline on the base branch first:
+#
+#     cargo bench --features bench --bench '*' -- --save-baseline main
+#
+# then run this script on the change. A benchmark fails the gate when the lower bound of its
+# mean's confidence interval is more than `threshold` (a fraction, 0.10 by default) above the
+# baseline, so noise alone does not fail CI. Requires jq.
+set -eu
+
+baseline=${1:-main}
+threshold=${2:-0.10}
+filter=${3:-}
+criterion=${CARGO_TARGET_DIR:-target}/criterion
+
+# Changes left over from earlier comparisons would be checked again.
+if [ -d "$criterion" ]; then
+    find "$criterion" -type d -name change -prune -exec rm -r {} +
+fi
+
+# shellcheck disable=SC2086
+cargo bench --features bench --bench '*' -- --baseline "$baseline" $filter
+
+failed=0
+for estimates in $(find "$criterion" -path '*/change/estimates.json' | sort); do
+    bench=${estimates#"$criterion"/}
+    bench=${bench%/change/estimates.json}
+    if jq -e --argjson max "$threshold" '.mean.confidence_interval.lower_bound > $max' \
+        "$estimates" >/dev/null; then
+        change=$(jq '.mean.point_estimate * 100 | round' "$estimates")
+        echo "regression: $bench is ${change}% slower than $baseline" >&2
+        failed=1
+    fi
+done
+exit $failed
@@ -0,0 +1,101 @@
+//! Alias keys that stand in for other keys.
+//!
+//! An alias is a table holding only an `__alias` key with the path of its target:
+//!
+//! ```toml
+//! [server]
+//! listen = { __alias = "server.address" }
+//! address = "0.0.0.0:8080"
+//! ```
+//!
+//! [`resolve`] follows aliases anywhere along a path, so a renamed table keeps working under
+//! its old name for every key in it.
+
+use crate::ops::set_field;
+use crate::path::KeyPath;
+use crate::walk::{walk, Node};
+use crate::{Error, Result};
+use toml_edit::{InlineTable, Item, Table, TableLike, Value};
+
+/// Key holding the target of an alias.
+pub const ALIAS_KEY: &str = "__alias";
+
+/// Returns the node at `path`, following aliases, together with the path it was found at.
+///
+/// Returns `None` if the path, or the target of an alias on it, does not exist. Fails with
+/// [`Error::AliasCycle`] if following aliases leads back to an alias already followed.
+pub fn resolve<'a>(table: &'a Table, path: &KeyPath) -> Result<Option<(KeyPath, Node<'a>)>> {
+    let mut followed: Vec<KeyPath> = vec![];
+    let mut current = path.clone();
+    'follow: loop {
+        let mut node = Node::Table(table);
+        let mut at = KeyPath::new();
+        for (i, segment) in current.segments().iter().enumerate() {
+            let Some(child) = node.child(segment) else {
+                return Ok(None);
+            };
+            node = child;
+            at.push(segment.clone());
+            if let Some(target) = target(node, &at)? {
+                if followed.contains(&at) {
+                    return Err(Error::AliasCycle { path: at });
+                }
+                followed.push(at);
+                let rest = current.segments()[i + 1..].iter().cloned();
+                current = target.segments().iter().cloned().chain(rest).collect();
+                continue 'follow;
+            }
+        }
+        return Ok(Some((current, node)));
+    }
+}
+
+/// Returns the target of `node` if it is an alias, where `at` is the path of the node.
+pub fn target(node: Node<'_>, at: &KeyPath) -> Result<Option<KeyPath>> {
+    let table: &dyn TableLike = match node {
+        Node::Table(table) => table,
+        Node::Value(Value::InlineTable(table)) => table,
+        _ => return Ok(None),
+    };
+    let Some(item) = table.get(ALIAS_KEY) else {
+        return Ok(None);
+    };
+    if table.len() > 1 {
+        return Err(Error::TypeMismatch {
+            path: at.clone(),
+            expected: "an alias with no keys besides `__alias`",
+            found: node.type_name(),
+        });
+    }
+    let target = item.as_str().ok_or_else(|| Error::TypeMismatch {
+        path: at.with_key(ALIAS_KEY),
+        expected: "a key path string",
+        found: item.type_name(),
+    })?;
+    KeyPath::parse(target).map(Some)
+}
+
+/// Makes `alias` an alias for `target`, returning the item it replaces.
+///
+/// The parent of `alias` must exist, but the target need not exist yet. Fails with
+/// [`Error::AliasCycle`], leaving `table` unchanged, if the alias would lead back to itself.
+pub fn add_alias(table: &mut Table, alias: &KeyPath, target: &KeyPath) -> Result<Option<Item>> {
+    let mut value = InlineTable::new();
+    value.insert(ALIAS_KEY, target.to_string().into());
+    let mut updated = table.clone();
+    let previous = set_field(&mut updated, alias, Value::InlineTable(value))?;
+    resolve(&updated, alias)?;
+    *table = updated;
+    Ok(previous)
+}
+
+/// Returns every alias below `table` with its target, in document order.
+pub fn aliases(table: &Table) -> Result<Vec<(KeyPath, KeyPath)>> {
+    let mut aliases = vec![];
+    for entry in walk(table) {
+        if let Some(target) = target(entry.node, &entry.path)? {
+            aliases.push((entry.path, target));
+        }
+    }
+    Ok(aliases)
+}
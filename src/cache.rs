@@ -0,0 +1,120 @@
+//! Caching of parsed documents.
+
+use crate::limits::Limits;
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use toml_edit::DocumentMut;
+
+/// Counters describing how a [`DocCache`] has been used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads that had to parse the file.
+    pub misses: u64,
+    /// Entries dropped by [`DocCache::invalidate`] or [`DocCache::clear`].
+    pub invalidations: u64,
+    /// Documents currently cached.
+    pub entries: usize,
+}
+
+/// A cache of parsed documents keyed by file path.
+///
+/// An entry is reused for as long as the file's modification time and length are unchanged. A
+/// write that keeps both the same, which is possible on file systems with coarse timestamps,
+/// is not noticed; writers that know they changed a file should call [`DocCache::invalidate`].
+///
+/// The cache can be shared between threads.
+#[derive(Debug, Default)]
+pub struct DocCache {
+    limits: Limits,
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    modified: SystemTime,
+    len: u64,
+    doc: Arc<DocumentMut>,
+}
+
+impl DocCache {
+    /// Creates an empty cache that reads documents with the default [`Limits`].
+    pub fn new() -> Self {
+        DocCache::default()
+    }
+
+    /// Creates an empty cache that reads documents with the given limits.
+    pub fn with_limits(limits: Limits) -> Self {
+        DocCache {
+            limits,
+            ..DocCache::default()
+        }
+    }
+
+    /// Returns the parsed document at `path`, parsing it only if it changed since it was cached.
+    pub fn get(&self, path: &Path) -> Result<Arc<DocumentMut>> {
+        // Stat before reading, so a write that lands during the read is picked up next time.
+        let meta = std::fs::metadata(path).map_err(Error::Io)?;
+        let modified = meta.modified().map_err(Error::Io)?;
+        let len = meta.len();
+        if let Some(entry) = self.lock().get(path) {
+            if entry.modified == modified && entry.len == len {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry.doc.clone());
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let doc = Arc::new(self.limits.read(path)?);
+        self.lock().insert(
+            path.to_path_buf(),
+            Entry {
+                modified,
+                len,
+                doc: doc.clone(),
+            },
+        );
+        Ok(doc)
+    }
+
+    /// Drops the cached document for `path`, returning whether there was one.
+    pub fn invalidate(&self, path: &Path) -> bool {
+        let removed = self.lock().remove(path).is_some();
+        if removed {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    /// Drops every cached document.
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        self.invalidations
+            .fetch_add(entries.len() as u64, Ordering::Relaxed);
+        entries.clear();
+    }
+
+    /// Returns the cache counters.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        // A panic while holding the lock cannot leave an entry half written, so the map is
+        // still consistent.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
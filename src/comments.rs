@@ -0,0 +1,405 @@
+//! Reading and writing comments attached to keys and tables.
+//!
+//! Comments live in the whitespace around keys, values and table headers. A comment is either
+//! written on the lines above a key or header, or after it on the same line:
+//!
+//! ```toml
+//! # Above the table.
+//! [server] # After the header.
+//! # Above the key.
+//! port = 8080 # After the value.
+//! ```
+//!
+//! Paths address keys of standard tables, standard tables themselves and elements of arrays of
+//! tables. Elements of arrays and keys of inline tables cannot carry comments.
+
+use crate::path::{KeyPath, Segment};
+use crate::walk::{get, get_mut, Node, NodeMut};
+use crate::{Error, Result};
+use toml_edit::{Decor, Item, Table};
+
+/// Where a comment is written relative to the key or header it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// On the lines directly above. May span several lines.
+    Above,
+    /// At the end of the same line. Limited to a single line.
+    After,
+}
+
+/// Returns the comment at `path`, without the leading `#`, or `None` if there is none.
+///
+/// Multi-line comments are joined with `\n`.
+pub fn comment(table: &Table, path: &KeyPath, placement: Placement) -> Result<Option<String>> {
+    let decor = target(table, path, placement)?;
+    Ok(match placement {
+        Placement::Above => {
+            let prefix = raw(decor.prefix()).unwrap_or_default();
+            let lines: Vec<&str> = prefix.lines().filter_map(comment_text).collect();
+            (!lines.is_empty()).then(|| lines.join("\n"))
+        }
+        Placement::After => {
+            comment_text(raw(decor.suffix()).unwrap_or_default()).map(str::to_string)
+        }
+    })
+}
+
+/// Sets the comment at `path`, replacing any comment already there.
+///
+/// `text` is given without the leading `#`. A comment placed [`Placement::After`] must be a
+/// single line, or [`Error::Unrepresentable`] is returned. Blank lines and indentation around
+/// the key or header are kept.
+pub fn set_comment(
+    table: &mut Table,
+    path: &KeyPath,
+    placement: Placement,
+    text: &str,
+) -> Result<()> {
+    if placement == Placement::After && text.contains(['\n', '\r']) {
+        return Err(Error::Unrepresentable {
+            path: path.clone(),
+            what: "multi-line comment after a value",
+        });
+    }
+    // A header is set apart from what comes before it by a blank line, unless nothing does.
+    let first = placement == Placement::Above && starts_document(table, path);
+    let written = with_decor(
+        table,
+        path,
+        placement,
+        true,
+        |decor, header| match placement {
+            Placement::Above => {
+                let default = if header && !first { "\n" } else { "" };
+                let prefix = raw(decor.prefix()).unwrap_or(default);
+                let (lines, indent) = split_indent(prefix);
+                let mut out: String = lines.filter(|l| comment_text(l).is_none()).collect();
+                for line in text.lines() {
+                    out.push_str(indent);
+                    match line {
+                        "" => out.push('#'),
+                        line => {
+                            out.push_str("# ");
+                            out.push_str(line);
+                        }
+                    }
+                    out.push('\n');
+                }
+                out.push_str(indent);
+                decor.set_prefix(out);
+                text.lines().next().is_some()
+            }
+            Placement::After => {
+                decor.set_suffix(format!(" # {text}"));
+                true
+            }
+        },
+    );
+    written.map(|_| ())
+}
+
+/// Removes the comment at `path`, returning whether there was one.
+///
+/// Blank lines and indentation around the key or header are kept.
+pub fn remove_comment(table: &mut Table, path: &KeyPath, placement: Placement) -> Result<bool> {
+    with_decor(table, path, placement, false, |decor, _| match placement {
+        Placement::Above => {
+            let Some(prefix) = raw(decor.prefix()) else {
+                return false;
+            };
+            let (lines, indent) = split_indent(prefix);
+            let mut removed = false;
+            let mut out = String::new();
+            for line in lines {
+                if comment_text(line).is_some() {
+                    removed = true;
+                } else {
+                    out.push_str(line);
+                }
+            }
+            out.push_str(indent);
+            decor.set_prefix(out);
+            removed
+        }
+        Placement::After => {
+            let removed = comment_text(raw(decor.suffix()).unwrap_or_default()).is_some();
+            if removed {
+                decor.set_suffix("");
+            }
+            removed
+        }
+    })
+}
+
+/// Resolves `path` to the decor holding its comment: that of the table header for tables, and
+/// that of the key or the value for plain values.
+fn target<'a>(table: &'a Table, path: &KeyPath, placement: Placement) -> Result<&'a Decor> {
+    let (parent_path, last) = split(path)?;
+    let missing = || Error::MissingKey { path: path.clone() };
+    match (get(table, &parent_path).ok_or_else(missing)?, last) {
+        (Node::Table(parent), Segment::Key(key)) => match parent.get(key).ok_or_else(missing)? {
+            Item::Table(table) => Ok(table.decor()),
+            Item::Value(value) if placement == Placement::After => Ok(value.decor()),
+            Item::Value(_) => Ok(parent.key(key).ok_or_else(missing)?.leaf_decor()),
+            item => Err(not_commentable(path.clone(), item.type_name())),
+        },
+        (Node::ArrayOfTables(array), Segment::Index(i)) => {
+            Ok(array.get(*i).ok_or_else(missing)?.decor())
+        }
+        (node, _) => Err(not_commentable(parent_path, node.type_name())),
+    }
+}
+
+/// Runs `f` on the decor [`target`] resolves `path` to, telling it whether that is the decor of
+/// a table header. `f` returns whether it wrote a comment, and with `make_explicit` an implicit
+/// table it wrote one for is given a header to carry it.
+fn with_decor(
+    table: &mut Table,
+    path: &KeyPath,
+    placement: Placement,
+    make_explicit: bool,
+    f: impl FnOnce(&mut Decor, bool) -> bool,
+) -> Result<bool> {
+    let (parent_path, last) = split(path)?;
+    let missing = || Error::MissingKey { path: path.clone() };
+    let table = match (get_mut(table, &parent_path).ok_or_else(missing)?, last) {
+        (NodeMut::Table(parent), Segment::Key(key)) => {
+            match parent.get_mut(key).ok_or_else(missing)? {
+                Item::Table(table) => table,
+                Item::Value(value) if placement == Placement::After => {
+                    return Ok(f(value.decor_mut(), false))
+                }
+                Item::Value(_) => {
+                    let mut key = parent.key_mut(key).ok_or_else(missing)?;
+                    return Ok(f(key.leaf_decor_mut(), false));
+                }
+                item => return Err(not_commentable(path.clone(), item.type_name())),
+            }
+        }
+        (NodeMut::ArrayOfTables(array), Segment::Index(i)) => {
+            array.get_mut(*i).ok_or_else(missing)?
+        }
+        (node, _) => return Err(not_commentable(parent_path, node.type_name())),
+    };
+    let written = f(table.decor_mut(), true);
+    if written && make_explicit {
+        table.set_implicit(false);
+    }
+    Ok(written)
+}
+
+/// Returns whether the table at `path` is the first thing written in the document, following
+/// the order toml_edit writes tables in.
+fn starts_document(root: &Table, path: &KeyPath) -> bool {
+    if root.iter().any(|(_, item)| in_body(item)) {
+        return false;
+    }
+    let mut headers = vec![];
+    collect_headers(root, &KeyPath::new(), false, path, &mut 0, &mut headers);
+    let first = headers.iter().min_by_key(|(position, _)| *position);
+    first.is_some_and(|(_, first)| first == path)
+}
+
+/// Collects the paths of the tables below `table` that are written with a header, along with
+/// the position they are written at. `path` is the table about to be given a header.
+fn collect_headers(
+    table: &Table,
+    at: &KeyPath,
+    array: bool,
+    path: &KeyPath,
+    last: &mut isize,
+    headers: &mut Vec<(isize, KeyPath)>,
+) {
+    if !table.is_dotted() && !at.is_empty() {
+        // Tables without a position of their own are written after the one before them.
+        if let Some(position) = table.position() {
+            *last = position;
+        }
+        let shown = array
+            || !table.is_implicit()
+            || at == path
+            || table.iter().any(|(_, item)| in_body(item));
+        if shown {
+            headers.push((*last, at.clone()));
+        }
+    }
+    for (key, item) in table.iter() {
+        match item {
+            Item::Table(child) => {
+                collect_headers(child, &at.with_key(key), false, path, last, headers)
+            }
+            Item::ArrayOfTables(array) => {
+                for (i, child) in array.iter().enumerate() {
+                    let at = at.with_key(key).with_index(i);
+                    collect_headers(child, &at, true, path, last, headers);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether `item` is written in the body of its table, rather than under a header.
+fn in_body(item: &Item) -> bool {
+    item.is_value() || item.as_table().is_some_and(Table::is_dotted)
+}
+
+fn split(path: &KeyPath) -> Result<(KeyPath, &Segment)> {
+    let last = path.last().ok_or(Error::EmptyPath)?;
+    Ok((path.parent().unwrap_or_default(), last))
+}
+
+fn not_commentable(path: KeyPath, found: &'static str) -> Error {
+    Error::TypeMismatch {
+        path,
+        expected: "a key or table",
+        found,
+    }
+}
+
+fn raw(s: Option<&toml_edit::RawString>) -> Option<&str> {
+    s.and_then(|s| s.as_str())
+}
+
+/// Returns the text of a comment line, or `None` if the line is not a comment.
+fn comment_text(line: &str) -> Option<&str> {
+    let text = line.trim().strip_prefix('#')?;
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+/// Splits a prefix into its full lines and the indentation after the last one.
+fn split_indent(prefix: &str) -> (impl Iterator<Item = &str>, &str) {
+    let end = prefix.rfind('\n').map_or(0, |i| i + 1);
+    (prefix[..end].split_inclusive('\n'), &prefix[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml_edit::DocumentMut;
+
+    /// Sets a comment on `path` in `src`, returning the rendered document.
+    fn set(src: &str, path: &str, placement: Placement, text: &str) -> String {
+        let mut doc: DocumentMut = src.parse().unwrap();
+        set_comment(&mut doc, &KeyPath::parse(path).unwrap(), placement, text).unwrap();
+        doc.to_string()
+    }
+
+    #[test]
+    fn reads_comments() {
+        let doc: DocumentMut =
+            "# t\n#\n# more\n[t] # header\n  # about x\n  x = 1 # one\n[[a]] # first\n"
+                .parse()
+                .unwrap();
+        let read = |path: &str, placement| {
+            comment(&doc, &KeyPath::parse(path).unwrap(), placement).unwrap()
+        };
+        assert_eq!(read("t", Placement::Above).as_deref(), Some("t\n\nmore"));
+        assert_eq!(read("t", Placement::After).as_deref(), Some("header"));
+        assert_eq!(read("t.x", Placement::Above).as_deref(), Some("about x"));
+        assert_eq!(read("t.x", Placement::After).as_deref(), Some("one"));
+        assert_eq!(read("a[0]", Placement::After).as_deref(), Some("first"));
+        assert_eq!(read("a[0]", Placement::Above), None);
+    }
+
+    #[test]
+    fn sets_comments_above() {
+        assert_eq!(
+            set("a = 1\n  b = 2\n", "b", Placement::Above, "two\n\nlines"),
+            "a = 1\n  # two\n  #\n  # lines\n  b = 2\n"
+        );
+        assert_eq!(
+            set("# old\n\nb = 2\n", "b", Placement::Above, "new"),
+            "\n# new\nb = 2\n"
+        );
+        assert_eq!(
+            set("a = 1\n[t]\nx = 1\n", "t", Placement::Above, "t"),
+            "a = 1\n# t\n[t]\nx = 1\n"
+        );
+    }
+
+    #[test]
+    fn sets_comments_after() {
+        assert_eq!(
+            set("a = 1 # old\n[t]\n", "a", Placement::After, "new"),
+            "a = 1 # new\n[t]\n"
+        );
+        assert_eq!(set("[t]\n", "t", Placement::After, "t"), "[t] # t\n");
+        let mut doc: DocumentMut = "a = 1\n".parse().unwrap();
+        let err = set_comment(
+            &mut doc,
+            &KeyPath::parse("a").unwrap(),
+            Placement::After,
+            "a\nb",
+        );
+        assert!(matches!(err, Err(Error::Unrepresentable { .. })));
+    }
+
+    #[test]
+    fn gives_implicit_tables_a_header() {
+        assert_eq!(
+            set("[a.b]\nx = 1\n", "a", Placement::Above, "a"),
+            "# a\n[a]\n[a.b]\nx = 1\n"
+        );
+        assert_eq!(
+            set("x = 1\n[a.b]\ny = 1\n", "a", Placement::Above, "a"),
+            "x = 1\n\n# a\n[a]\n[a.b]\ny = 1\n"
+        );
+        // Nothing is written for an empty comment, so the table stays implicit.
+        assert_eq!(
+            set("[a.b]\nx = 1\n", "a", Placement::Above, ""),
+            "[a.b]\nx = 1\n"
+        );
+    }
+
+    #[test]
+    fn comments_on_a_header_starting_the_document_add_no_blank_line() {
+        let mut table = Table::new();
+        table.insert("a", Item::Table(Table::new()));
+        table["a"]["x"] = toml_edit::value(1);
+        table.insert("b", Item::Table(Table::new()));
+        for (path, expected) in [
+            ("a", "# top\n[a]\nx = 1\n\n[b]\n"),
+            ("b", "[a]\nx = 1\n\n# top\n[b]\n"),
+        ] {
+            let mut table = table.clone();
+            set_comment(
+                &mut table,
+                &KeyPath::parse(path).unwrap(),
+                Placement::Above,
+                "top",
+            )
+            .unwrap();
+            assert_eq!(DocumentMut::from(table).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn removes_comments() {
+        let mut doc: DocumentMut = "# t\n[t] # header\n# x\nx = 1 # one\ny = 2\n"
+            .parse()
+            .unwrap();
+        let remove = |doc: &mut DocumentMut, path: &str, placement| {
+            remove_comment(doc, &KeyPath::parse(path).unwrap(), placement).unwrap()
+        };
+        assert!(remove(&mut doc, "t", Placement::Above));
+        assert!(remove(&mut doc, "t", Placement::After));
+        assert!(remove(&mut doc, "t.x", Placement::Above));
+        assert!(remove(&mut doc, "t.x", Placement::After));
+        assert!(!remove(&mut doc, "t.y", Placement::After));
+        assert_eq!(doc.to_string(), "[t]\nx = 1\ny = 2\n");
+    }
+
+    #[test]
+    fn rejects_what_cannot_carry_comments() {
+        let mut doc: DocumentMut = "a = [1]\nt = { x = 1 }\n".parse().unwrap();
+        for (path, placement) in [("a[0]", Placement::Above), ("t.x", Placement::After)] {
+            let err = set_comment(&mut doc, &KeyPath::parse(path).unwrap(), placement, "c");
+            assert!(matches!(err, Err(Error::TypeMismatch { .. })), "{path}");
+        }
+        let err = comment(&doc, &KeyPath::parse("z").unwrap(), Placement::Above);
+        assert!(matches!(err, Err(Error::MissingKey { .. })));
+        let err = comment(&doc, &KeyPath::new(), Placement::Above);
+        assert!(matches!(err, Err(Error::EmptyPath)));
+    }
+}
@@ -0,0 +1,578 @@
+//! Constraints on the values stored under keys.
+//!
+//! Constraints are kept in the document they apply to, in a table mapping key paths to rules:
+//!
+//! ```toml
+//! [__constraints]
+//! "server.port" = { type = "integer", min = 1, max = 65535 }
+//! "server.host" = { type = "string", pattern = "^[a-z0-9.-]+$", required = true }
+//! "log.level" = { one_of = ["debug", "info", "warn"] }
+//! ```
+//!
+//! [`Constraints::check`] reports every violation in a document, and writers call
+//! [`Constraints::check_value`] before storing a value so that invalid values are rejected with
+//! [`Error::ConstraintViolation`].
+
+use crate::ops::value_eq;
+use crate::path::KeyPath;
+use crate::walk::{get, Node};
+use crate::{Error, Result};
+use regex::Regex;
+use std::fmt;
+use toml_edit::{Array, InlineTable, Item, Table, TableLike, Value};
+
+/// Name of the table holding the constraints of a document.
+pub const TABLE: &str = "__constraints";
+
+/// The type a constrained value must have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    String,
+    Integer,
+    Float,
+    /// An integer or a float.
+    Number,
+    Boolean,
+    Datetime,
+    /// An array or an array of tables.
+    Array,
+    /// A table or an inline table.
+    Table,
+}
+
+impl Kind {
+    /// Returns the kind with the given name, also accepting `int`, `bool` and `obj`.
+    pub fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "string" => Some(Kind::String),
+            "integer" | "int" => Some(Kind::Integer),
+            "float" => Some(Kind::Float),
+            "number" => Some(Kind::Number),
+            "boolean" | "bool" => Some(Kind::Boolean),
+            "datetime" => Some(Kind::Datetime),
+            "array" => Some(Kind::Array),
+            "table" | "obj" | "object" => Some(Kind::Table),
+            _ => None,
+        }
+    }
+
+    /// Returns the name the kind is stored as.
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::String => "string",
+            Kind::Integer => "integer",
+            Kind::Float => "float",
+            Kind::Number => "number",
+            Kind::Boolean => "boolean",
+            Kind::Datetime => "datetime",
+            Kind::Array => "array",
+            Kind::Table => "table",
+        }
+    }
+
+    fn matches(self, node: Node<'_>) -> bool {
+        matches!(
+            (self, node),
+            (
+                Kind::Table,
+                Node::Table(_) | Node::Value(Value::InlineTable(_))
+            ) | (
+                Kind::Array,
+                Node::ArrayOfTables(_) | Node::Value(Value::Array(_))
+            ) | (Kind::String, Node::Value(Value::String(_)))
+                | (Kind::Integer, Node::Value(Value::Integer(_)))
+                | (Kind::Float, Node::Value(Value::Float(_)))
+                | (
+                    Kind::Number,
+                    Node::Value(Value::Integer(_) | Value::Float(_))
+                )
+                | (Kind::Boolean, Node::Value(Value::Boolean(_)))
+                | (Kind::Datetime, Node::Value(Value::Datetime(_)))
+        )
+    }
+}
+
+/// The rules for the value under one key. Rules that are `None` are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Constraint {
+    /// Type the value must have.
+    pub kind: Option<Kind>,
+    /// Smallest number allowed.
+    pub min: Option<f64>,
+    /// Largest number allowed.
+    pub max: Option<f64>,
+    /// Regular expression strings must match.
+    pub pattern: Option<Regex>,
+    /// The values allowed, compared ignoring formatting.
+    pub one_of: Option<Vec<Value>>,
+    /// Whether the key must exist.
+    pub required: bool,
+}
+
+impl Constraint {
+    /// Checks the node found under the constrained key, returning what is wrong with it.
+    fn check(&self, node: Option<Node<'_>>) -> Option<String> {
+        let Some(node) = node else {
+            return self.required.then(|| "is required".to_string());
+        };
+        if let Some(kind) = self.kind {
+            if !kind.matches(node) {
+                return Some(format!(
+                    "must be of type {}, found {}",
+                    kind.name(),
+                    node.type_name()
+                ));
+            }
+        }
+        let value = match node {
+            Node::Value(value) => Some(value),
+            _ => None,
+        };
+        if self.min.is_some() || self.max.is_some() {
+            let n = match value {
+                Some(Value::Integer(i)) => *i.value() as f64,
+                Some(Value::Float(f)) => *f.value(),
+                _ => return Some(format!("must be a number, found {}", node.type_name())),
+            };
+            // NaN compares false with everything, so it would otherwise pass any bound.
+            if n.is_nan() {
+                return Some("must be a number in range, found nan".to_string());
+            }
+            if let Some(min) = self.min.filter(|min| n < *min) {
+                return Some(format!("must be at least {min}, found {n}"));
+            }
+            if let Some(max) = self.max.filter(|max| n > *max) {
+                return Some(format!("must be at most {max}, found {n}"));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            let Some(Value::String(s)) = value else {
+                return Some(format!("must be a string, found {}", node.type_name()));
+            };
+            if !pattern.is_match(s.value()) {
+                return Some(format!("must match `{pattern}`"));
+            }
+        }
+        if let Some(allowed) = &self.one_of {
+            let found = value.is_some_and(|value| allowed.iter().any(|a| value_eq(a, value)));
+            if !found {
+                let allowed: Vec<String> = allowed.iter().map(bare).collect();
+                return Some(format!("must be one of {}", allowed.join(", ")));
+            }
+        }
+        None
+    }
+
+    /// Reads a constraint from its definition, where `at` is the path of the definition.
+    fn from_item(item: &Item, at: &KeyPath) -> Result<Constraint> {
+        let invalid = |field: &str, reason: String| Error::InvalidConstraint {
+            path: at.with_key(field),
+            reason,
+        };
+        let Some(table) = item.as_table_like() else {
+            return Err(Error::TypeMismatch {
+                path: at.clone(),
+                expected: "a table",
+                found: item.type_name(),
+            });
+        };
+        let mut constraint = Constraint::default();
+        for (field, item) in table.iter() {
+            let value = item.as_value();
+            match field {
+                "type" => {
+                    let name = value.and_then(Value::as_str);
+                    let kind = name.and_then(Kind::from_name);
+                    constraint.kind = Some(kind.ok_or_else(|| {
+                        invalid(field, "expected a type name such as `integer`".to_string())
+                    })?);
+                }
+                "min" | "max" => {
+                    let n = match value {
+                        Some(Value::Integer(i)) => *i.value() as f64,
+                        Some(Value::Float(f)) => *f.value(),
+                        _ => return Err(invalid(field, "expected a number".to_string())),
+                    };
+                    if field == "min" {
+                        constraint.min = Some(n);
+                    } else {
+                        constraint.max = Some(n);
+                    }
+                }
+                "pattern" => {
+                    let pattern = value
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| invalid(field, "expected a string".to_string()))?;
+                    let regex = Regex::new(pattern).map_err(|e| invalid(field, e.to_string()))?;
+                    constraint.pattern = Some(regex);
+                }
+                "one_of" => {
+                    let array = value
+                        .and_then(Value::as_array)
+                        .ok_or_else(|| invalid(field, "expected an array".to_string()))?;
+                    constraint.one_of = Some(array.iter().cloned().collect());
+                }
+                "required" => {
+                    constraint.required = value
+                        .and_then(Value::as_bool)
+                        .ok_or_else(|| invalid(field, "expected a boolean".to_string()))?;
+                }
+                _ => return Err(invalid(field, "unknown field".to_string())),
+            }
+        }
+        Ok(constraint)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut table = InlineTable::new();
+        if let Some(kind) = self.kind {
+            table.insert("type", kind.name().into());
+        }
+        if let Some(min) = self.min {
+            table.insert("min", number(min));
+        }
+        if let Some(max) = self.max {
+            table.insert("max", number(max));
+        }
+        if let Some(pattern) = &self.pattern {
+            table.insert("pattern", pattern.as_str().into());
+        }
+        if let Some(allowed) = &self.one_of {
+            let mut array: Array = allowed.iter().cloned().collect();
+            array.fmt();
+            table.insert("one_of", Value::Array(array));
+        }
+        if self.required {
+            table.insert("required", true.into());
+        }
+        table.fmt();
+        Value::InlineTable(table)
+    }
+}
+
+/// What [`check_shape`] found at a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shape {
+    /// The node exists and has the expected type and fields.
+    Ok,
+    /// Nothing exists at the path.
+    Missing,
+    /// The node has a different type than expected.
+    WrongType { found: &'static str },
+    /// The node is a table lacking the listed fields.
+    MissingFields(Vec<String>),
+}
+
+/// Checks that the node at `path` exists, is of type `kind` if one is given, and is a table with
+/// every key in `fields`.
+///
+/// Asking for fields implies the node must be a table or inline table.
+pub fn check_shape(doc: &Table, path: &KeyPath, kind: Option<Kind>, fields: &[&str]) -> Shape {
+    let Some(node) = get(doc, path) else {
+        return Shape::Missing;
+    };
+    if kind.is_some_and(|kind| !kind.matches(node)) {
+        return Shape::WrongType {
+            found: node.type_name(),
+        };
+    }
+    if fields.is_empty() {
+        return Shape::Ok;
+    }
+    let table: &dyn TableLike = match node {
+        Node::Table(table) => table,
+        Node::Value(Value::InlineTable(table)) => table,
+        node => {
+            return Shape::WrongType {
+                found: node.type_name(),
+            }
+        }
+    };
+    let missing: Vec<String> = fields
+        .iter()
+        .filter(|field| !table.contains_key(field))
+        .map(|field| field.to_string())
+        .collect();
+    if missing.is_empty() {
+        Shape::Ok
+    } else {
+        Shape::MissingFields(missing)
+    }
+}
+
+/// A value that does not satisfy its constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub path: KeyPath,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` {}", self.path, self.message)
+    }
+}
+
+/// The constraints of a document, keyed by path.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    rules: Vec<(KeyPath, Constraint)>,
+}
+
+impl Constraints {
+    /// Creates an empty set of constraints.
+    pub fn new() -> Self {
+        Constraints::default()
+    }
+
+    /// Reads the constraints stored in the [`TABLE`] table of `doc`.
+    ///
+    /// A document without that table has no constraints.
+    pub fn from_document(doc: &Table) -> Result<Constraints> {
+        let mut constraints = Constraints::new();
+        let Some(item) = doc.get(TABLE) else {
+            return Ok(constraints);
+        };
+        let at = KeyPath::new().with_key(TABLE);
+        let Some(table) = item.as_table_like() else {
+            return Err(Error::TypeMismatch {
+                path: at,
+                expected: "a table",
+                found: item.type_name(),
+            });
+        };
+        for (key, item) in table.iter() {
+            let path = KeyPath::parse(key)?;
+            let constraint = Constraint::from_item(item, &at.with_key(key))?;
+            constraints.add(path, constraint);
+        }
+        Ok(constraints)
+    }
+
+    /// Writes the constraints to the [`TABLE`] table of `doc`, removing the table if there are
+    /// none.
+    pub fn store(&self, doc: &mut Table) {
+        if self.rules.is_empty() {
+            doc.remove(TABLE);
+            return;
+        }
+        if !doc.get(TABLE).is_some_and(Item::is_table) {
+            doc.insert(TABLE, toml_edit::table());
+        }
+        let table = doc[TABLE].as_table_mut().expect("constraints table exists");
+        table.clear();
+        for (path, constraint) in &self.rules {
+            table.insert(&path.to_string(), Item::Value(constraint.to_value()));
+        }
+    }
+
+    /// Sets the constraint for `path`, returning the one it replaces.
+    pub fn add(&mut self, path: KeyPath, constraint: Constraint) -> Option<Constraint> {
+        match self.rules.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, existing)) => Some(std::mem::replace(existing, constraint)),
+            None => {
+                self.rules.push((path, constraint));
+                None
+            }
+        }
+    }
+
+    /// Removes the constraint for `path`, returning it.
+    pub fn remove(&mut self, path: &KeyPath) -> Option<Constraint> {
+        let i = self.rules.iter().position(|(p, _)| p == path)?;
+        Some(self.rules.remove(i).1)
+    }
+
+    /// Returns the constraint for `path`.
+    pub fn get(&self, path: &KeyPath) -> Option<&Constraint> {
+        self.rules.iter().find(|(p, _)| p == path).map(|(_, c)| c)
+    }
+
+    /// Returns the constraints in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyPath, &Constraint)> {
+        self.rules.iter().map(|(p, c)| (p, c))
+    }
+
+    /// Returns whether there are no constraints.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks every constraint against `doc`.
+    pub fn check(&self, doc: &Table) -> Vec<Violation> {
+        self.rules
+            .iter()
+            .filter_map(|(path, constraint)| {
+                constraint.check(get(doc, path)).map(|message| Violation {
+                    path: path.clone(),
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks a value about to be written at `path`.
+    ///
+    /// Constraints on keys inside `value` are checked too, so writing an inline table that
+    /// lacks a required key fails.
+    pub fn check_value(&self, path: &KeyPath, value: &Value) -> Result<()> {
+        for (rule, constraint) in &self.rules {
+            let Some(rest) = rule.segments().strip_prefix(path.segments()) else {
+                continue;
+            };
+            let rest: KeyPath = rest.iter().cloned().collect();
+            if let Some(message) = constraint.check(Node::Value(value).get(&rest)) {
+                return Err(Error::ConstraintViolation {
+                    path: rule.clone(),
+                    message,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders a value without its surrounding whitespace and comments.
+fn bare(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string()
+}
+
+/// Stores whole numbers as integers, so `min = 1` round-trips as written.
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml_edit::DocumentMut;
+
+    const RULES: &str = r#"
+[__constraints]
+"server.port" = { type = "int", min = 1, max = 65535 }
+"server.host" = { type = "string", pattern = "^[a-z0-9.-]+$", required = true }
+"log.level" = { one_of = ["debug", "info"] }
+"#;
+
+    fn check(src: &str) -> Vec<String> {
+        let doc: DocumentMut = format!("{src}{RULES}").parse().unwrap();
+        let constraints = Constraints::from_document(&doc).unwrap();
+        let violations = constraints.check(&doc);
+        violations.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parses_and_stores_constraints() {
+        let doc: DocumentMut = RULES.parse().unwrap();
+        let constraints = Constraints::from_document(&doc).unwrap();
+        assert_eq!(constraints.iter().count(), 3);
+        let port = constraints
+            .get(&KeyPath::parse("server.port").unwrap())
+            .unwrap();
+        assert_eq!(port.kind, Some(Kind::Integer));
+        assert_eq!((port.min, port.max), (Some(1.0), Some(65535.0)));
+        let mut stored = DocumentMut::new();
+        constraints.store(&mut stored);
+        assert_eq!(
+            stored.to_string(),
+            "[__constraints]\n\
+             \"server.port\" = { type = \"integer\", min = 1, max = 65535 }\n\
+             \"server.host\" = { type = \"string\", pattern = \"^[a-z0-9.-]+$\", required = true }\n\
+             \"log.level\" = { one_of = [\"debug\", \"info\"] }\n"
+        );
+        assert!(Constraints::from_document(&DocumentMut::new())
+            .unwrap()
+            .is_empty());
+        Constraints::new().store(&mut stored);
+        assert_eq!(stored.to_string(), "");
+    }
+
+    #[test]
+    fn rejects_invalid_definitions() {
+        let cases = [
+            ("x = { type = \"text\" }", "__constraints.x.type"),
+            ("x = { min = \"1\" }", "__constraints.x.min"),
+            ("x = { pattern = \"(\" }", "__constraints.x.pattern"),
+            ("x = { one_of = 1 }", "__constraints.x.one_of"),
+            ("x = { required = 1 }", "__constraints.x.required"),
+            ("x = { maximum = 1 }", "__constraints.x.maximum"),
+        ];
+        for (rule, at) in cases {
+            let doc: DocumentMut = format!("[__constraints]\n{rule}\n").parse().unwrap();
+            match Constraints::from_document(&doc) {
+                Err(Error::InvalidConstraint { path, .. }) => assert_eq!(path.to_string(), at),
+                other => panic!("{rule}: {other:?}"),
+            }
+        }
+        let doc: DocumentMut = "__constraints = 1\n".parse().unwrap();
+        let err = Constraints::from_document(&doc).unwrap_err();
+        assert!(matches!(err, Error::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn reports_violations() {
+        let valid = "[server]\nport = 80\nhost = \"example.com\"\n[log]\nlevel = \"info\"\n";
+        assert!(check(valid).is_empty());
+        assert_eq!(check(""), vec!["`server.host` is required"]);
+        assert_eq!(
+            check("[server]\nport = 0\nhost = \"Example\"\n[log]\nlevel = 'warn'\n"),
+            vec![
+                "`server.port` must be at least 1, found 0",
+                "`server.host` must match `^[a-z0-9.-]+$`",
+                "`log.level` must be one of \"debug\", \"info\"",
+            ]
+        );
+        assert_eq!(
+            check("[server]\nport = \"80\"\nhost = \"h\"\n"),
+            vec!["`server.port` must be of type integer, found string"]
+        );
+        assert_eq!(
+            check("[server]\nport = nan\nhost = \"h\"\n"),
+            vec!["`server.port` must be of type integer, found float"]
+        );
+    }
+
+    #[test]
+    fn checks_values_before_writing() {
+        let doc: DocumentMut = RULES.parse().unwrap();
+        let constraints = Constraints::from_document(&doc).unwrap();
+        let port = KeyPath::parse("server.port").unwrap();
+        assert!(constraints.check_value(&port, &Value::from(8080)).is_ok());
+        let err = constraints.check_value(&port, &Value::from(70000));
+        assert!(matches!(err, Err(Error::ConstraintViolation { .. })));
+        // Required keys inside a written table are checked too.
+        let server = KeyPath::parse("server").unwrap();
+        let table: Value = "{ port = 80 }".parse().unwrap();
+        let err = constraints.check_value(&server, &table).unwrap_err();
+        assert_eq!(err.to_string(), "`server.host` is required");
+    }
+
+    #[test]
+    fn checks_shapes() {
+        let doc: DocumentMut = "[a]\nx = 1\nb = { y = 2 }\n".parse().unwrap();
+        let at = |s: &str| KeyPath::parse(s).unwrap();
+        assert_eq!(
+            check_shape(&doc, &at("a"), Some(Kind::Table), &["x"]),
+            Shape::Ok
+        );
+        assert_eq!(
+            check_shape(&doc, &at("a.b"), None, &["y", "z"]),
+            Shape::MissingFields(vec!["z".to_string()])
+        );
+        assert_eq!(
+            check_shape(&doc, &at("a.x"), None, &["y"]),
+            Shape::WrongType { found: "integer" }
+        );
+        assert_eq!(
+            check_shape(&doc, &at("a.x"), Some(Kind::Number), &[]),
+            Shape::Ok
+        );
+        assert_eq!(check_shape(&doc, &at("c"), None, &[]), Shape::Missing);
+    }
+}
@@ -0,0 +1,185 @@
+//! Deprecation metadata for keys.
+//!
+//! Deprecated keys are listed in the document they apply to, each with an optional replacement
+//! and note:
+//!
+//! ```toml
+//! [__deprecated]
+//! "server.listen" = { replacement = "server.address", note = "renamed in 2.0" }
+//! "legacy" = {}
+//! ```
+//!
+//! Deprecating a table deprecates every key in it. Reads through [`Deprecations::get`] still
+//! succeed, but come with a [`Warning`] to pass on to the user.
+
+use crate::path::KeyPath;
+use crate::walk::{get, Node};
+use crate::{Error, Result};
+use std::fmt;
+use toml_edit::{InlineTable, Item, Table, Value};
+
+/// Name of the table listing the deprecated keys of a document.
+pub const TABLE: &str = "__deprecated";
+
+/// Why a key is deprecated and what to use instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Key to use instead.
+    pub replacement: Option<KeyPath>,
+    /// Free-form explanation.
+    pub note: Option<String>,
+}
+
+/// A use of a deprecated key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The key that was used.
+    pub path: KeyPath,
+    /// The key to use instead. For a key in a deprecated table, this is the same key in the
+    /// replacement table.
+    pub replacement: Option<KeyPath>,
+    pub note: Option<String>,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is deprecated", self.path)?;
+        if let Some(replacement) = &self.replacement {
+            write!(f, ", use `{replacement}` instead")?;
+        }
+        if let Some(note) = &self.note {
+            write!(f, " ({note})")?;
+        }
+        Ok(())
+    }
+}
+
+/// The deprecated keys of a document.
+#[derive(Debug, Clone, Default)]
+pub struct Deprecations {
+    entries: Vec<(KeyPath, Deprecation)>,
+}
+
+impl Deprecations {
+    /// Creates an empty set of deprecations.
+    pub fn new() -> Self {
+        Deprecations::default()
+    }
+
+    /// Reads the deprecations listed in the [`TABLE`] table of `doc`.
+    pub fn from_document(doc: &Table) -> Result<Deprecations> {
+        let mut deprecations = Deprecations::new();
+        let Some(item) = doc.get(TABLE) else {
+            return Ok(deprecations);
+        };
+        let at = KeyPath::new().with_key(TABLE);
+        let table = item.as_table_like().ok_or_else(|| Error::TypeMismatch {
+            path: at.clone(),
+            expected: "a table",
+            found: item.type_name(),
+        })?;
+        for (key, item) in table.iter() {
+            let at = at.with_key(key);
+            let entry = item.as_table_like().ok_or_else(|| Error::TypeMismatch {
+                path: at.clone(),
+                expected: "a table",
+                found: item.type_name(),
+            })?;
+            let mut deprecation = Deprecation::default();
+            for (field, item) in entry.iter() {
+                let at = at.with_key(field);
+                if !matches!(field, "replacement" | "note") {
+                    return Err(Error::InvalidDeprecation {
+                        path: at,
+                        reason: "unknown field".to_string(),
+                    });
+                }
+                let text = item.as_str().ok_or_else(|| Error::TypeMismatch {
+                    path: at.clone(),
+                    expected: "a string",
+                    found: item.type_name(),
+                })?;
+                if field == "replacement" {
+                    deprecation.replacement = Some(KeyPath::parse(text)?);
+                } else {
+                    deprecation.note = Some(text.to_string());
+                }
+            }
+            deprecations.add(KeyPath::parse(key)?, deprecation);
+        }
+        Ok(deprecations)
+    }
+
+    /// Writes the deprecations to the [`TABLE`] table of `doc`, removing the table if there
+    /// are none.
+    pub fn store(&self, doc: &mut Table) {
+        if self.entries.is_empty() {
+            doc.remove(TABLE);
+            return;
+        }
+        if !doc.get(TABLE).is_some_and(Item::is_table) {
+            doc.insert(TABLE, toml_edit::table());
+        }
+        let table = doc[TABLE]
+            .as_table_mut()
+            .expect("deprecations table exists");
+        table.clear();
+        for (path, deprecation) in &self.entries {
+            let mut entry = InlineTable::new();
+            if let Some(replacement) = &deprecation.replacement {
+                entry.insert("replacement", replacement.to_string().into());
+            }
+            if let Some(note) = &deprecation.note {
+                entry.insert("note", note.as_str().into());
+            }
+            entry.fmt();
+            table.insert(&path.to_string(), Item::Value(Value::InlineTable(entry)));
+        }
+    }
+
+    /// Marks `path` as deprecated, returning the deprecation it replaces.
+    pub fn add(&mut self, path: KeyPath, deprecation: Deprecation) -> Option<Deprecation> {
+        match self.entries.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, existing)) => Some(std::mem::replace(existing, deprecation)),
+            None => {
+                self.entries.push((path, deprecation));
+                None
+            }
+        }
+    }
+
+    /// Removes the deprecation of `path`, returning it.
+    pub fn remove(&mut self, path: &KeyPath) -> Option<Deprecation> {
+        let i = self.entries.iter().position(|(p, _)| p == path)?;
+        Some(self.entries.remove(i).1)
+    }
+
+    /// Returns the warning for using `path`, if it or a table containing it is deprecated.
+    pub fn warning(&self, path: &KeyPath) -> Option<Warning> {
+        self.entries.iter().find_map(|(deprecated, deprecation)| {
+            let rest = path.segments().strip_prefix(deprecated.segments())?;
+            Some(Warning {
+                path: path.clone(),
+                replacement: deprecation.replacement.as_ref().map(|replacement| {
+                    replacement.segments().iter().chain(rest).cloned().collect()
+                }),
+                note: deprecation.note.clone(),
+            })
+        })
+    }
+
+    /// Returns the node at `path` below `doc` like [`get`], along with a warning if the key is
+    /// deprecated.
+    pub fn get<'a>(&self, doc: &'a Table, path: &KeyPath) -> (Option<Node<'a>>, Option<Warning>) {
+        (get(doc, path), self.warning(path))
+    }
+
+    /// Returns a warning for every deprecated key that is set in `doc`.
+    pub fn check(&self, doc: &Table) -> Vec<Warning> {
+        self.entries
+            .iter()
+            .filter(|(path, _)| get(doc, path).is_some())
+            .filter_map(|(path, _)| self.warning(path))
+            .collect()
+    }
+}
@@ -0,0 +1,386 @@
+//! Finding and repairing conflicting table and key definitions.
+//!
+//! A document that defines a table or key twice, usually the result of a bad merge or of
+//! concatenating files, is rejected by the parser, so it cannot be loaded to be fixed. [`diagnose`] finds such
+//! conflicts in the source text with the lexer from [`scan`](crate::scan), and [`repair`]
+//! rewrites the source to resolve the ones that can be resolved without guessing.
+
+use crate::path::{KeyPath, Segment};
+use crate::scan::{sections, Section};
+use crate::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use toml_edit::DocumentMut;
+
+/// The kind of conflict found by [`diagnose`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProblemKind {
+    /// A `[table]` header appears more than once.
+    DuplicateTable,
+    /// A table is both a `[table]` and an `[[array of tables]]`.
+    TableAndArray,
+    /// A table is defined both by dotted keys and by its own header.
+    DottedAndHeader,
+    /// A table header extends a table that is written as an inline table.
+    InlineAndHeader,
+    /// A key is set more than once in the same table, or is set to a value and also used as a
+    /// table by dotted keys.
+    DuplicateKey,
+}
+
+impl ProblemKind {
+    /// Returns whether [`repair`] resolves conflicts of this kind.
+    pub fn is_repairable(self) -> bool {
+        self == ProblemKind::DuplicateTable
+    }
+}
+
+/// A conflicting definition found by [`diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// The table or key that is defined more than once.
+    pub path: KeyPath,
+    /// Line of the definition that conflicts with an earlier one, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            ProblemKind::DuplicateTable => "is defined by more than one header",
+            ProblemKind::TableAndArray => "is both a table and an array of tables",
+            ProblemKind::DottedAndHeader => "is defined by both dotted keys and a header",
+            ProblemKind::InlineAndHeader => "is an inline table and cannot be extended by a header",
+            ProblemKind::DuplicateKey => "is set more than once",
+        };
+        write!(f, "line {}: `{}` {what}", self.line, self.path)
+    }
+}
+
+/// Finds the conflicting table and key definitions in `src`, in the order they appear.
+///
+/// Fails with the parser's error if `src` is not lexically valid TOML, since the conflicts cannot
+/// be told apart from the rest of the document then. A document without conflicts may still fail
+/// to parse for other reasons.
+pub fn diagnose(src: &str) -> Result<Vec<Problem>> {
+    let Some(sections) = sections(src) else {
+        return src
+            .parse::<DocumentMut>()
+            .map(|_| vec![])
+            .map_err(Error::Toml);
+    };
+    let mut headers: HashMap<KeyPath, bool> = HashMap::new();
+    let mut dotted: HashSet<KeyPath> = HashSet::new();
+    let mut inline: HashSet<KeyPath> = HashSet::new();
+    let mut problems = vec![];
+    let mut report = |kind, path: &KeyPath, offset: usize| {
+        let problem = Problem {
+            kind,
+            path: path.clone(),
+            line: src[..offset].matches('\n').count() + 1,
+        };
+        if !problems
+            .iter()
+            .any(|p: &Problem| p.kind == kind && p.path == *path)
+        {
+            problems.push(problem);
+        }
+    };
+
+    for (i, section) in sections.iter().enumerate() {
+        let path = &section.path;
+        for (key, start) in repeated_keys(section) {
+            report(ProblemKind::DuplicateKey, &key, start);
+        }
+        if i > 0 {
+            match headers.get(path) {
+                Some(false) if !section.array => {
+                    report(ProblemKind::DuplicateTable, path, section.start)
+                }
+                Some(array) if *array != section.array => {
+                    report(ProblemKind::TableAndArray, path, section.start)
+                }
+                Some(_) => {}
+                None => {
+                    headers.insert(path.clone(), section.array);
+                }
+            }
+            if section.array {
+                // Headers below an array of tables belong to its new element from here on.
+                forget_below(&mut headers, path);
+                dotted.retain(|p| !is_below(p, path));
+                inline.retain(|p| !is_below(p, path));
+            }
+            if dotted.contains(path) {
+                report(ProblemKind::DottedAndHeader, path, section.start);
+            }
+            if let Some(table) = inline
+                .iter()
+                .find(|p| path.segments().starts_with(p.segments()))
+            {
+                let table = table.clone();
+                report(ProblemKind::InlineAndHeader, &table, section.start);
+            }
+        }
+        if section.array {
+            // Keys of an array element do not define tables that other sections can reach.
+            continue;
+        }
+        for key in &section.keys {
+            let segments = key.path.segments();
+            for len in 1..segments.len() {
+                let table: KeyPath = path
+                    .segments()
+                    .iter()
+                    .chain(&segments[..len])
+                    .cloned()
+                    .collect();
+                if headers.get(&table) == Some(&false) {
+                    report(ProblemKind::DottedAndHeader, &table, key.start);
+                }
+                dotted.insert(table);
+            }
+            if key.inline_table {
+                let table: KeyPath = path.segments().iter().chain(segments).cloned().collect();
+                if headers
+                    .keys()
+                    .any(|p| p.segments().starts_with(table.segments()))
+                {
+                    report(ProblemKind::InlineAndHeader, &table, key.start);
+                }
+                inline.insert(table);
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Rewrites `src` so that every table with more than one `[table]` header is written under its
+/// first header, with the bodies of the later ones appended to it in order. Returns the new
+/// source, which can be shown as a preview before it is written.
+///
+/// Fails with the parser's error if the result still does not parse, for example because it has
+/// conflicts of a kind that is not repaired, or because the merged tables set the same key.
+pub fn repair(src: &str) -> Result<String> {
+    let Some(sections) = sections(src) else {
+        return src
+            .parse::<DocumentMut>()
+            .map(|_| src.to_string())
+            .map_err(Error::Toml);
+    };
+    // Later sections with the same header, by the index of the first one.
+    let mut merged: HashMap<usize, Vec<&Section>> = HashMap::new();
+    let mut skip = HashSet::new();
+    let mut first: HashMap<KeyPath, usize> = HashMap::new();
+    for (i, section) in sections.iter().enumerate().skip(1) {
+        if section.array {
+            forget_below(&mut first, &section.path);
+            continue;
+        }
+        match first.get(&section.path) {
+            Some(&j) => {
+                merged.entry(j).or_default().push(section);
+                skip.insert(i);
+            }
+            None => {
+                first.insert(section.path.clone(), i);
+            }
+        }
+    }
+
+    let mut out = String::with_capacity(src.len());
+    for (i, section) in sections.iter().enumerate() {
+        if skip.contains(&i) {
+            continue;
+        }
+        out.push_str(&src[section.start..section.end]);
+        for later in merged.get(&i).into_iter().flatten() {
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            // The rest of the dropped header line, if it is only whitespace.
+            let body = src[later.body..later.end].trim_start_matches([' ', '\t']);
+            let body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+            out.push_str(body);
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    out.parse::<DocumentMut>().map_err(Error::Toml)?;
+    Ok(out)
+}
+
+/// Returns the keys of `section` that are set more than once, as absolute paths, with the offset
+/// of the key that repeats them.
+///
+/// A key also repeats a key that is a dotted prefix of it, or of which it is a dotted prefix:
+/// `a = 1` conflicts with `a.b = 2` whichever comes first, while `a.b` and `a.c` can coexist.
+fn repeated_keys(section: &Section) -> Vec<(KeyPath, usize)> {
+    let mut values: HashSet<&[Segment]> = HashSet::new();
+    let mut tables: HashSet<&[Segment]> = HashSet::new();
+    let mut repeated = vec![];
+    for key in &section.keys {
+        let segments = key.path.segments();
+        let prefixes = (1..segments.len()).map(|len| &segments[..len]);
+        let clash = prefixes
+            .clone()
+            .find(|prefix| values.contains(prefix))
+            .or_else(|| {
+                (values.contains(segments) || tables.contains(segments)).then_some(segments)
+            });
+        if let Some(clash) = clash {
+            let path = section
+                .path
+                .segments()
+                .iter()
+                .chain(clash)
+                .cloned()
+                .collect();
+            repeated.push((path, key.start));
+        }
+        tables.extend(prefixes);
+        values.insert(segments);
+    }
+    repeated
+}
+
+/// Returns whether `path` is strictly below `table`.
+fn is_below(path: &KeyPath, table: &KeyPath) -> bool {
+    path.len() > table.len() && path.segments().starts_with(table.segments())
+}
+
+fn forget_below<V>(map: &mut HashMap<KeyPath, V>, table: &KeyPath) {
+    map.retain(|path, _| !is_below(path, table));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(src: &str) -> Vec<(ProblemKind, String, usize)> {
+        diagnose(src)
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.kind, p.path.to_string(), p.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_documents_have_no_problems() {
+        let src = "a.b = 1\n[x]\nk.v = 1\n[x.k.sub]\nd = 1\n[[arr]]\n[arr.t]\n[[arr]]\n[arr.t]\n";
+        assert!(src.parse::<DocumentMut>().is_ok());
+        assert_eq!(problems(src), vec![]);
+        assert_eq!(repair(src).unwrap(), src);
+    }
+
+    #[test]
+    fn duplicate_tables_are_merged_into_the_first() {
+        let src = "[a]\nb = 1 # x\n\n# about c\n[a] # second\nc = 1\n[z]\nq = 1\n[a]\nd = 2";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::DuplicateTable, "a".to_string(), 5)]
+        );
+        let repaired = repair(src).unwrap();
+        assert_eq!(
+            repaired,
+            "[a]\nb = 1 # x\n\n# about c\n# second\nc = 1\nd = 2\n[z]\nq = 1\n"
+        );
+        assert_eq!(problems(&repaired), vec![]);
+    }
+
+    #[test]
+    fn duplicate_keys_in_merged_tables_are_not_repaired() {
+        let src = "[a]\nx = 1\n[a]\nx = 2\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::DuplicateTable, "a".to_string(), 3)]
+        );
+        assert!(matches!(repair(src), Err(Error::Toml(_))));
+    }
+
+    #[test]
+    fn table_and_array_of_tables() {
+        let src = "[[a]]\nx = 1\n[a]\ny = 1\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::TableAndArray, "a".to_string(), 3)]
+        );
+        assert!(!ProblemKind::TableAndArray.is_repairable());
+        assert!(repair(src).is_err());
+    }
+
+    #[test]
+    fn dotted_keys_and_headers() {
+        // Dotted keys first.
+        let src = "a.b = 1\n[a]\nc = 1\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::DottedAndHeader, "a".to_string(), 2)]
+        );
+        assert!(repair(src).is_err());
+        // Header first.
+        let src = "[a.b]\nx = 1\n[a]\nb.y = 2\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::DottedAndHeader, "a.b".to_string(), 4)]
+        );
+    }
+
+    #[test]
+    fn headers_extending_inline_tables() {
+        let src = "a = { b = 1 }\n[a.c]\nx = 1\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::InlineAndHeader, "a".to_string(), 2)]
+        );
+        let src = "[a.c]\nx = 1\n[b]\n[x]\n";
+        assert_eq!(problems(src), vec![]);
+        let src = "[t.c]\nx = 1\n[t]\nc = {}\n";
+        assert_eq!(
+            problems(src),
+            vec![(ProblemKind::InlineAndHeader, "t.c".to_string(), 4)]
+        );
+        assert!(repair(src).is_err());
+    }
+
+    #[test]
+    fn lexically_invalid_documents_report_the_parse_error() {
+        let src = "a = \"unterminated\n";
+        assert!(matches!(diagnose(src), Err(Error::Toml(_))));
+        assert!(matches!(repair(src), Err(Error::Toml(_))));
+    }
+
+    #[test]
+    fn reports_repeated_keys() {
+        let cases = [
+            ("x = 1\nx = 2\n", "x", 2),
+            ("[a]\nx = 1\ny = 2\nx = 3\n", "a.x", 4),
+            ("[a]\n\"x\" = 1\nx = 2\n", "a.x", 3),
+            ("a.b = 1\na = 2\n", "a", 2),
+            ("a = 1\na.b = 2\n", "a", 2),
+            ("a = { b = 1 }\na.c = 2\n", "a", 2),
+            ("a.b.c = 1\na.b = 2\n", "a.b", 2),
+            ("[[t]]\nx = 1\nx = 2\n", "t.x", 3),
+        ];
+        for (src, path, line) in cases {
+            assert_eq!(
+                problems(src),
+                vec![(ProblemKind::DuplicateKey, path.to_string(), line)],
+                "{src:?}"
+            );
+            assert!(!ProblemKind::DuplicateKey.is_repairable());
+            assert!(repair(src).is_err());
+        }
+    }
+
+    #[test]
+    fn dotted_keys_sharing_a_prefix_are_not_repeated() {
+        let src = "a.b = 1\na.c = 2\nd = 1\n[[t]]\nx = 1\n[[t]]\nx = 2\n[u]\nx = 1\n";
+        assert_eq!(problems(src), vec![]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Environment variable overrides for document values.
+//!
+//! A value at `server.port` can be overridden with the variable `TOMLDB__SERVER__PORT`: the
+//! prefix, then each key of the path upper-cased, separated by double underscores. Characters
+//! other than ASCII letters and digits become underscores, and array indices are written as
+//! numbers.
+//!
+//! Variable values are parsed as TOML values, so `8080` is an integer and `[1, 2]` an array.
+//! Anything that does not parse is taken as a plain string, and so is every variable
+//! overriding a value that is a string in the document.
+//!
+//! [`export`] goes the other way, writing the values of a table as variables named the same way.
+
+use crate::path::{KeyPath, Segment};
+use crate::template::{shell_quote, RenderScalar};
+use crate::walk::{get, is_metadata, walk, Node};
+use std::collections::HashMap;
+use toml_edit::{Table, Value};
+
+/// Prefix used by [`EnvOverrides::from_env`].
+pub const DEFAULT_PREFIX: &str = "TOMLDB";
+
+/// Where a value was found by [`EnvOverrides::get`].
+#[derive(Debug, Clone)]
+pub enum Resolved<'a> {
+    /// The value came from the named environment variable.
+    Env { var: String, value: Value },
+    /// The value came from the document.
+    File(Node<'a>),
+}
+
+impl Resolved<'_> {
+    /// Returns `"env"` or `"file"`.
+    pub fn source(&self) -> &'static str {
+        match self {
+            Resolved::Env { .. } => "env",
+            Resolved::File(_) => "file",
+        }
+    }
+}
+
+/// A snapshot of the environment variables overriding document values.
+#[derive(Debug, Clone, Default)]
+pub struct EnvOverrides {
+    prefix: String,
+    vars: HashMap<String, String>,
+}
+
+impl EnvOverrides {
+    /// Captures the variables starting with `TOMLDB__` from the process environment.
+    pub fn from_env() -> Self {
+        Self::with_prefix(DEFAULT_PREFIX)
+    }
+
+    /// Captures the variables starting with `prefix` and a double underscore from the process
+    /// environment.
+    pub fn with_prefix(prefix: &str) -> Self {
+        Self::from_vars(prefix, std::env::vars())
+    }
+
+    /// Uses the given variables instead of the process environment. Variables without the
+    /// prefix are ignored.
+    pub fn from_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let start = format!("{prefix}__");
+        EnvOverrides {
+            prefix: prefix.to_string(),
+            vars: vars
+                .into_iter()
+                .filter(|(name, _)| name.starts_with(&start))
+                .collect(),
+        }
+    }
+
+    /// Returns the name of the variable overriding the value at `path`.
+    pub fn var_name(&self, path: &KeyPath) -> String {
+        var_name(Some(&self.prefix), path)
+    }
+
+    /// Returns the value at `path`, taking it from the environment if a variable overrides it
+    /// and from `doc` otherwise.
+    pub fn get<'a>(&self, doc: &'a Table, path: &KeyPath) -> Option<Resolved<'a>> {
+        let node = get(doc, path);
+        let var = self.var_name(path);
+        let Some(raw) = self.vars.get(&var) else {
+            return node.map(Resolved::File);
+        };
+        let value = match node {
+            Some(Node::Value(Value::String(_))) => Value::from(raw.as_str()),
+            _ => raw
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| Value::from(raw.as_str())),
+        };
+        Some(Resolved::Env { var, value })
+    }
+
+    /// Returns whether any variable overrides a value.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+}
+
+/// How [`export`] writes variables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportStyle {
+    /// `KEY=value` lines for dotenv files, double-quoting values that need it.
+    #[default]
+    Dotenv,
+    /// `export KEY=value` lines that a POSIX shell can `eval`.
+    Shell,
+}
+
+/// Writes every scalar value below `table` as a variable assignment, one per line.
+///
+/// Variables are named as for overrides, relative to `table` and starting with `prefix` if one is
+/// given: `port` in a table exported with prefix `APP` becomes `APP__PORT`. Array elements are
+/// written one per index, and empty arrays and tables produce nothing. Metadata, as told by
+/// [`is_metadata`], is not exported.
+pub fn export(table: &Table, prefix: Option<&str>, style: ExportStyle) -> String {
+    let mut out = String::new();
+    for entry in walk(table).filter(|entry| !is_metadata(&entry.path)) {
+        let Node::Value(value) = entry.node else {
+            continue;
+        };
+        let Some(text) = value.render_scalar() else {
+            continue;
+        };
+        let name = var_name(prefix, &entry.path);
+        let plain = !text.is_empty()
+            && text
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-./:,+@%".contains(c));
+        match style {
+            ExportStyle::Dotenv if plain => out.push_str(&format!("{name}={text}\n")),
+            ExportStyle::Dotenv => out.push_str(&format!("{name}={}\n", dotenv_quote(&text))),
+            ExportStyle::Shell if plain => out.push_str(&format!("export {name}={text}\n")),
+            ExportStyle::Shell => out.push_str(&format!("export {name}={}\n", shell_quote(&text))),
+        }
+    }
+    out
+}
+
+fn var_name(prefix: Option<&str>, path: &KeyPath) -> String {
+    let mut parts: Vec<String> = prefix.map(str::to_string).into_iter().collect();
+    for segment in path.segments() {
+        parts.push(match segment {
+            Segment::Key(key) => key
+                .chars()
+                .map(|c| match c {
+                    c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+                    _ => '_',
+                })
+                .collect(),
+            Segment::Index(i) => i.to_string(),
+        });
+    }
+    parts.join("__")
+}
+
+/// Double-quotes `s` the way dotenv parsers read it back.
+fn dotenv_quote(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '\\' | '"' | '$' | '`' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,132 @@
+use crate::path::KeyPath;
+use std::fmt;
+
+/// Result type used throughout tomldb.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by tomldb.
+#[derive(Debug)]
+pub enum Error {
+    /// Following aliases led back to an alias already followed.
+    AliasCycle { path: KeyPath },
+    /// A value does not satisfy a constraint attached to its key.
+    ConstraintViolation { path: KeyPath, message: String },
+    /// An empty key path was given where at least one key is required.
+    EmptyPath,
+    /// A constraint definition is malformed.
+    InvalidConstraint { path: KeyPath, reason: String },
+    /// A deprecation definition is malformed.
+    InvalidDeprecation { path: KeyPath, reason: String },
+    /// A key path could not be parsed.
+    InvalidPath {
+        input: String,
+        offset: usize,
+        reason: &'static str,
+    },
+    /// A template could not be parsed.
+    InvalidTemplate { offset: usize, reason: &'static str },
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// A JSON document could not be parsed or produced.
+    Json(serde_json::Error),
+    /// A document or value is larger than a configured limit allows.
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+        actual: usize,
+        path: KeyPath,
+    },
+    /// A migration failed.
+    Migration { name: String, source: Box<Error> },
+    /// A key or array element on the path does not exist.
+    MissingKey { path: KeyPath },
+    /// A value on the path exists but has the wrong type.
+    TypeMismatch {
+        path: KeyPath,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A TOML document or value could not be parsed.
+    Toml(toml_edit::TomlError),
+    /// A data format name was not recognized.
+    UnknownFormat { name: String },
+    /// A value has no equivalent in the target format.
+    Unrepresentable { path: KeyPath, what: &'static str },
+    /// A YAML document could not be parsed or produced.
+    Yaml(serde_norway::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AliasCycle { path } => write!(f, "alias `{path}` leads back to itself"),
+            Error::ConstraintViolation { path, message } => write!(f, "`{path}` {message}"),
+            Error::EmptyPath => write!(f, "key path is empty"),
+            Error::InvalidConstraint { path, reason } => {
+                write!(f, "invalid constraint at `{path}`: {reason}")
+            }
+            Error::InvalidDeprecation { path, reason } => {
+                write!(f, "invalid deprecation at `{path}`: {reason}")
+            }
+            Error::InvalidPath {
+                input,
+                offset,
+                reason,
+            } => write!(f, "invalid key path `{input}` at offset {offset}: {reason}"),
+            Error::InvalidTemplate { offset, reason } => {
+                write!(f, "invalid template at offset {offset}: {reason}")
+            }
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Json(err) => write!(f, "{err}"),
+            Error::LimitExceeded {
+                limit,
+                max,
+                actual,
+                path,
+            } if path.is_empty() => write!(f, "{limit} of {actual} exceeds the limit of {max}"),
+            Error::LimitExceeded {
+                limit,
+                max,
+                actual,
+                path,
+            } => write!(
+                f,
+                "{limit} of {actual} at `{path}` exceeds the limit of {max}"
+            ),
+            Error::Migration { name, source } => write!(f, "migration `{name}` failed: {source}"),
+            Error::MissingKey { path } => write!(f, "`{path}` does not exist"),
+            Error::TypeMismatch {
+                path,
+                expected,
+                found,
+            } if path.is_empty() => write!(f, "document is of type {found}, expected {expected}"),
+            Error::TypeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(f, "`{path}` is of type {found}, expected {expected}"),
+            Error::Toml(err) => write!(f, "{err}"),
+            Error::UnknownFormat { name } => write!(f, "unknown format `{name}`"),
+            Error::Unrepresentable { path, what } if path.is_empty() => {
+                write!(f, "{what} cannot be represented")
+            }
+            Error::Unrepresentable { path, what } => {
+                write!(f, "{what} at `{path}` cannot be represented")
+            }
+            Error::Yaml(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            Error::Migration { source, .. } => Some(source.as_ref()),
+            Error::Toml(err) => Some(err),
+            Error::Yaml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
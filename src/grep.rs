@@ -0,0 +1,45 @@
+//! Searching documents by key and string content.
+
+use crate::path::KeyPath;
+use crate::walk::{walk, Node};
+use regex::Regex;
+use toml_edit::{Table, Value};
+
+/// What part of an entry a [`GrepMatch`] was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchOn {
+    Key,
+    Value,
+}
+
+/// An entry whose key or string value matched the pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub path: KeyPath,
+    pub on: MatchOn,
+}
+
+/// Searches every key and string value below `table` for `pattern`.
+///
+/// Matches are returned in document order, with paths relative to `table`. An entry whose key
+/// and value both match is reported twice, once for each.
+pub fn grep(table: &Table, pattern: &Regex) -> Vec<GrepMatch> {
+    let mut matches = vec![];
+    for entry in walk(table) {
+        if entry.key.is_some_and(|key| pattern.is_match(key)) {
+            matches.push(GrepMatch {
+                path: entry.path.clone(),
+                on: MatchOn::Key,
+            });
+        }
+        if let Node::Value(Value::String(s)) = entry.node {
+            if pattern.is_match(s.value()) {
+                matches.push(GrepMatch {
+                    path: entry.path,
+                    on: MatchOn::Value,
+                });
+            }
+        }
+    }
+    matches
+}
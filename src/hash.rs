@@ -0,0 +1,75 @@
+//! Hashing documents by content.
+//!
+//! [`content_hash`] hashes what a document means rather than how it is written, so two hosts
+//! whose configuration differs only in formatting, comments or key order produce the same hash.
+
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use toml_edit::{Item, Table, TableLike, Value};
+
+/// Returns the SHA-256 hash of the content of `table`, as lowercase hex.
+///
+/// Whitespace, comments, key order, string syntax and number notation do not affect the hash,
+/// and an array of tables hashes the same as an array of inline tables with the same content.
+/// Integers and floats are distinct: `1` and `1.0` hash differently.
+pub fn content_hash(table: &Table) -> String {
+    let mut canonical = String::new();
+    write_table(&mut canonical, table);
+    let digest = Sha256::digest(canonical.as_bytes());
+    digest
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Writes a canonical, unambiguous form of the table: entries sorted by key, every value tagged
+/// with its type.
+fn write_table(out: &mut String, table: &dyn TableLike) {
+    let mut entries: Vec<(&str, &Item)> = table.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    out.push('{');
+    for (key, item) in entries {
+        let _ = write!(out, "{key:?}=");
+        match item {
+            Item::None => {}
+            Item::Value(value) => write_value(out, value),
+            Item::Table(table) => write_table(out, table),
+            Item::ArrayOfTables(array) => {
+                out.push('[');
+                for table in array.iter() {
+                    write_table(out, table);
+                    out.push(',');
+                }
+                out.push(']');
+            }
+        }
+        out.push(',');
+    }
+    out.push('}');
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    let _ = match value {
+        Value::String(s) => write!(out, "s{:?}", s.value()),
+        Value::Integer(i) => write!(out, "i{}", i.value()),
+        Value::Float(f) if f.value().is_nan() => write!(out, "fnan"),
+        Value::Float(f) => write!(out, "f{:?}", f.value()),
+        Value::Boolean(b) => write!(out, "b{}", b.value()),
+        Value::Datetime(dt) => write!(out, "d{}", dt.value()),
+        Value::Array(array) => {
+            out.push('[');
+            for value in array.iter() {
+                write_value(out, value);
+                out.push(',');
+            }
+            out.push(']');
+            Ok(())
+        }
+        Value::InlineTable(table) => {
+            write_table(out, table);
+            Ok(())
+        }
+    };
+}
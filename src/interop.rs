@@ -0,0 +1,510 @@
+//! Conversion between TOML and other data formats.
+//!
+//! JSON and YAML values are converted structurally: objects and mappings become inline tables,
+//! sequences become arrays. Values that TOML cannot hold, such as `null`, are rejected with
+//! [`Error::Unrepresentable`] rather than dropped. In the other direction, datetimes become
+//! strings.
+
+use crate::path::KeyPath;
+use crate::{Error, Result};
+use std::path::Path;
+use std::str::FromStr;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+
+/// A data format that can be converted to and from TOML.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "toml" => Ok(Format::Toml),
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(Error::UnknownFormat {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl Format {
+    /// Picks the format matching the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Format> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        ext.parse()
+    }
+}
+
+/// Parses `input` as a single value in the given format.
+pub fn parse_item(input: &str, format: Format) -> Result<Item> {
+    match format {
+        Format::Toml => input.parse::<Value>().map(Item::Value).map_err(Error::Toml),
+        Format::Json => json_to_item(&serde_json::from_str(input).map_err(Error::Json)?),
+        Format::Yaml => yaml_to_item(&serde_norway::from_str(input).map_err(Error::Yaml)?),
+    }
+}
+
+/// Converts a JSON value into the equivalent TOML item.
+pub fn json_to_item(json: &serde_json::Value) -> Result<Item> {
+    json_to_value(json).map(Item::Value)
+}
+
+/// Converts a JSON value into the equivalent TOML value.
+pub fn json_to_value(json: &serde_json::Value) -> Result<Value> {
+    from_json(json, &KeyPath::new())
+}
+
+/// Converts a YAML value into the equivalent TOML item.
+///
+/// Mapping keys must be strings, or [`Error::Unrepresentable`] is returned. Tags are ignored.
+/// `.inf`, `-.inf` and `.nan` become the TOML floats `inf`, `-inf` and `nan`.
+pub fn yaml_to_item(yaml: &serde_norway::Value) -> Result<Item> {
+    from_yaml(yaml, &KeyPath::new()).map(Item::Value)
+}
+
+/// Converts a TOML item into the equivalent JSON value.
+pub fn item_to_json(item: &Item) -> Result<serde_json::Value> {
+    item_to_json_at(item, &KeyPath::new())
+}
+
+/// Converts a TOML value into the equivalent JSON value.
+pub fn value_to_json(value: &Value) -> Result<serde_json::Value> {
+    to_json(value, &KeyPath::new())
+}
+
+/// Converts a TOML item into the equivalent YAML value.
+///
+/// YAML can hold every TOML value, including `inf`, `-inf` and `nan`, which become `.inf`,
+/// `-.inf` and `.nan`, so this never fails.
+pub fn item_to_yaml(item: &Item) -> Result<serde_norway::Value> {
+    Ok(item_yaml(item))
+}
+
+/// Parses `input` as a whole document in the given format.
+///
+/// For JSON and YAML the top level must be an object. Nested objects become standard tables and
+/// arrays of objects become arrays of tables, so the result reads like hand-written TOML. Key
+/// order is preserved, except that TOML requires plain values to come before tables.
+pub fn parse_document(input: &str, format: Format) -> Result<DocumentMut> {
+    match format {
+        Format::Toml => input.parse().map_err(Error::Toml),
+        Format::Json => json_to_document(&serde_json::from_str(input).map_err(Error::Json)?),
+        Format::Yaml => {
+            let yaml: serde_norway::Value = serde_norway::from_str(input).map_err(Error::Yaml)?;
+            value_to_document(from_yaml(&yaml, &KeyPath::new())?)
+        }
+    }
+}
+
+/// Converts a JSON object into a TOML document.
+pub fn json_to_document(json: &serde_json::Value) -> Result<DocumentMut> {
+    value_to_document(json_to_value(json)?)
+}
+
+/// Turns an inline table converted from another format into a document.
+fn value_to_document(value: Value) -> Result<DocumentMut> {
+    let table = match value {
+        Value::InlineTable(table) => table,
+        value => {
+            return Err(Error::TypeMismatch {
+                path: KeyPath::new(),
+                expected: "a table",
+                found: value.type_name(),
+            })
+        }
+    };
+    let mut table = table.into_table();
+    expand_tables(&mut table);
+    Ok(DocumentMut::from(table))
+}
+
+/// Renders a document in the given format.
+pub fn render_document(doc: &DocumentMut, format: Format) -> Result<String> {
+    match format {
+        Format::Toml => Ok(doc.to_string()),
+        Format::Json => {
+            let json = item_to_json(doc.as_item())?;
+            let mut out = serde_json::to_string_pretty(&json).map_err(Error::Json)?;
+            out.push('\n');
+            Ok(out)
+        }
+        Format::Yaml => serde_norway::to_string(&item_to_yaml(doc.as_item())?).map_err(Error::Yaml),
+    }
+}
+
+/// Converts the file at `input` into the file at `output`, picking both formats from the
+/// file extensions (`.toml`, `.json`, `.yaml` or `.yml`).
+pub fn convert_file(input: &Path, output: &Path) -> Result<()> {
+    let from = Format::from_path(input)?;
+    let to = Format::from_path(output)?;
+    let src = std::fs::read_to_string(input).map_err(Error::Io)?;
+    let out = render_document(&parse_document(&src, from)?, to)?;
+    std::fs::write(output, out).map_err(Error::Io)
+}
+
+/// Turns inline tables into standard tables, and arrays of inline tables into arrays of
+/// tables, all the way down.
+fn expand_tables(table: &mut Table) {
+    table.fmt();
+    for (_, item) in table.iter_mut() {
+        *item = match std::mem::take(item) {
+            Item::Value(Value::InlineTable(inline)) => {
+                let mut table = inline.into_table();
+                expand_tables(&mut table);
+                Item::Table(table)
+            }
+            Item::Value(Value::Array(array))
+                if !array.is_empty() && array.iter().all(Value::is_inline_table) =>
+            {
+                let mut tables = ArrayOfTables::new();
+                for value in array {
+                    if let Value::InlineTable(inline) = value {
+                        let mut table = inline.into_table();
+                        expand_tables(&mut table);
+                        tables.push(table);
+                    }
+                }
+                Item::ArrayOfTables(tables)
+            }
+            item => item,
+        };
+    }
+}
+
+fn from_json(json: &serde_json::Value, path: &KeyPath) -> Result<Value> {
+    use serde_json::Value as Json;
+
+    let value = match json {
+        Json::Null => {
+            return Err(Error::Unrepresentable {
+                path: path.clone(),
+                what: "null",
+            })
+        }
+        Json::Bool(b) => Value::from(*b),
+        Json::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Value::from(i),
+            (None, _) if n.is_u64() => {
+                return Err(Error::Unrepresentable {
+                    path: path.clone(),
+                    what: "integer larger than i64::MAX",
+                })
+            }
+            (None, Some(f)) => Value::from(f),
+            (None, None) => {
+                return Err(Error::Unrepresentable {
+                    path: path.clone(),
+                    what: "number",
+                })
+            }
+        },
+        Json::String(s) => Value::from(s.as_str()),
+        Json::Array(elements) => {
+            let mut array = Array::new();
+            for (i, element) in elements.iter().enumerate() {
+                array.push(from_json(element, &path.with_index(i))?);
+            }
+            Value::Array(array)
+        }
+        Json::Object(entries) => {
+            let mut table = InlineTable::new();
+            for (key, entry) in entries {
+                table.insert(key, from_json(entry, &path.with_key(key))?);
+            }
+            Value::InlineTable(table)
+        }
+    };
+    Ok(value)
+}
+
+fn from_yaml(yaml: &serde_norway::Value, path: &KeyPath) -> Result<Value> {
+    use serde_norway::Value as Yaml;
+
+    let value = match yaml {
+        Yaml::Null => {
+            return Err(Error::Unrepresentable {
+                path: path.clone(),
+                what: "null",
+            })
+        }
+        Yaml::Bool(b) => Value::from(*b),
+        Yaml::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Value::from(i),
+            (None, _) if n.is_u64() => {
+                return Err(Error::Unrepresentable {
+                    path: path.clone(),
+                    what: "integer larger than i64::MAX",
+                })
+            }
+            (None, Some(f)) => Value::from(f),
+            (None, None) => {
+                return Err(Error::Unrepresentable {
+                    path: path.clone(),
+                    what: "number",
+                })
+            }
+        },
+        Yaml::String(s) => Value::from(s.as_str()),
+        Yaml::Sequence(elements) => {
+            let mut array = Array::new();
+            for (i, element) in elements.iter().enumerate() {
+                array.push(from_yaml(element, &path.with_index(i))?);
+            }
+            Value::Array(array)
+        }
+        Yaml::Mapping(entries) => {
+            let mut table = InlineTable::new();
+            for (key, entry) in entries {
+                let Yaml::String(key) = key else {
+                    return Err(Error::Unrepresentable {
+                        path: path.clone(),
+                        what: "non-string mapping key",
+                    });
+                };
+                table.insert(key, from_yaml(entry, &path.with_key(key))?);
+            }
+            Value::InlineTable(table)
+        }
+        Yaml::Tagged(tagged) => from_yaml(&tagged.value, path)?,
+    };
+    Ok(value)
+}
+
+fn item_yaml(item: &Item) -> serde_norway::Value {
+    use serde_norway::Value as Yaml;
+
+    let table_yaml = |table: &Table| {
+        let entries = table
+            .iter()
+            .map(|(key, item)| (key.into(), item_yaml(item)));
+        Yaml::Mapping(entries.collect())
+    };
+    match item {
+        Item::None => Yaml::Null,
+        Item::Value(value) => to_yaml(value),
+        Item::Table(table) => table_yaml(table),
+        Item::ArrayOfTables(array) => Yaml::Sequence(array.iter().map(table_yaml).collect()),
+    }
+}
+
+fn to_yaml(value: &Value) -> serde_norway::Value {
+    use serde_norway::Value as Yaml;
+
+    match value {
+        Value::String(s) => Yaml::from(s.value().as_str()),
+        Value::Integer(i) => Yaml::from(*i.value()),
+        Value::Float(f) => Yaml::from(*f.value()),
+        Value::Boolean(b) => Yaml::from(*b.value()),
+        Value::Datetime(dt) => Yaml::from(dt.value().to_string()),
+        Value::Array(array) => Yaml::Sequence(array.iter().map(to_yaml).collect()),
+        Value::InlineTable(table) => Yaml::Mapping(
+            table
+                .iter()
+                .map(|(key, value)| (key.into(), to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn item_to_json_at(item: &Item, path: &KeyPath) -> Result<serde_json::Value> {
+    match item {
+        Item::None => Ok(serde_json::Value::Null),
+        Item::Value(value) => to_json(value, path),
+        Item::Table(table) => table_to_json(table, path),
+        Item::ArrayOfTables(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, table)| table_to_json(table, &path.with_index(i)))
+            .collect::<Result<_>>()
+            .map(serde_json::Value::Array),
+    }
+}
+
+fn table_to_json(table: &Table, path: &KeyPath) -> Result<serde_json::Value> {
+    let mut object = serde_json::Map::new();
+    for (key, item) in table.iter() {
+        object.insert(key.to_string(), item_to_json_at(item, &path.with_key(key))?);
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+fn to_json(value: &Value, path: &KeyPath) -> Result<serde_json::Value> {
+    use serde_json::Value as Json;
+
+    let json = match value {
+        Value::String(s) => Json::from(s.value().as_str()),
+        Value::Integer(i) => Json::from(*i.value()),
+        Value::Float(f) => serde_json::Number::from_f64(*f.value())
+            .map(Json::Number)
+            .ok_or_else(|| Error::Unrepresentable {
+                path: path.clone(),
+                what: "non-finite float",
+            })?,
+        Value::Boolean(b) => Json::from(*b.value()),
+        Value::Datetime(dt) => Json::from(dt.value().to_string()),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, value)| to_json(value, &path.with_index(i)))
+            .collect::<Result<_>>()
+            .map(Json::Array)?,
+        Value::InlineTable(table) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in table.iter() {
+                object.insert(key.to_string(), to_json(value, &path.with_key(key))?);
+            }
+            Json::Object(object)
+        }
+    };
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::toml_eq;
+
+    const TOML: &str = "\
+title = \"t\"
+when = 1979-05-27T07:32:00Z
+
+[server]
+port = 80
+ratio = 1.5
+
+[server.limits]
+tags = [\"a\", \"b\"]
+
+[[hosts]]
+name = \"a\"
+
+[[hosts]]
+name = \"b\"
+";
+
+    /// [`TOML`] as read back from JSON or YAML, where datetimes have no form of their own and
+    /// arrive as strings.
+    fn imported() -> DocumentMut {
+        let toml = TOML.replace("= 1979-05-27T07:32:00Z", "= \"1979-05-27T07:32:00Z\"");
+        toml.parse().unwrap()
+    }
+
+    #[test]
+    fn imports_nested_tables_and_arrays_of_tables() {
+        let json = r#"{
+            "title": "t",
+            "server": {"port": 80, "ratio": 1.5, "limits": {"tags": ["a", "b"]}},
+            "hosts": [{"name": "a"}, {"name": "b"}],
+            "when": "1979-05-27T07:32:00Z"
+        }"#;
+        let yaml = "\
+title: t
+server:
+  port: 80
+  ratio: 1.5
+  limits: {tags: [a, b]}
+hosts:
+  - name: a
+  - name: b
+when: 1979-05-27T07:32:00Z
+";
+        for (input, format) in [(json, Format::Json), (yaml, Format::Yaml)] {
+            let doc = parse_document(input, format).unwrap();
+            assert!(toml_eq(doc.as_item(), imported().as_item()), "{format:?}");
+            assert!(doc.get("server").unwrap().is_table());
+            assert!(doc.get("hosts").unwrap().is_array_of_tables());
+        }
+    }
+
+    #[test]
+    fn imports_yaml_non_finite_floats() {
+        let doc = parse_document("a: .inf\nb: -.inf\nc: .nan\n", Format::Yaml).unwrap();
+        assert_eq!(doc.to_string(), "a = inf\nb = -inf\nc = nan\n");
+        let item = parse_item("[.inf, 1]", Format::Yaml).unwrap();
+        assert_eq!(item.to_string(), "[inf, 1]");
+    }
+
+    #[test]
+    fn rejects_what_toml_cannot_hold() {
+        let cases = [
+            ("{\"a\": null}", Format::Json),
+            ("{\"a\": 18446744073709551615}", Format::Json),
+            ("a: ~", Format::Yaml),
+            ("a: {1: b}", Format::Yaml),
+        ];
+        for (input, format) in cases {
+            let err = parse_document(input, format).unwrap_err();
+            assert!(matches!(err, Error::Unrepresentable { .. }), "{input}");
+        }
+        let err = parse_document("[1]", Format::Json).unwrap_err();
+        assert!(matches!(err, Error::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn renders_json() {
+        let doc: DocumentMut = TOML.parse().unwrap();
+        let json = render_document(&doc, Format::Json).unwrap();
+        let expected = serde_json::json!({
+            "title": "t",
+            "when": "1979-05-27T07:32:00Z",
+            "server": {"port": 80, "ratio": 1.5, "limits": {"tags": ["a", "b"]}},
+            "hosts": [{"name": "a"}, {"name": "b"}],
+        });
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            expected
+        );
+        assert!(json.ends_with("}\n"));
+        let doc: DocumentMut = "a = [1.0, nan]\n".parse().unwrap();
+        match render_document(&doc, Format::Json) {
+            Err(Error::Unrepresentable { path, .. }) => assert_eq!(path.to_string(), "a[1]"),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_yaml() {
+        let doc: DocumentMut = TOML.parse().unwrap();
+        let yaml = render_document(&doc, Format::Yaml).unwrap();
+        assert!(yaml.contains("when: 1979-05-27T07:32:00Z\n"), "{yaml}");
+        let back = parse_document(&yaml, Format::Yaml).unwrap();
+        assert!(toml_eq(back.as_item(), imported().as_item()));
+        assert_eq!(render_document(&doc, Format::Toml).unwrap(), TOML);
+    }
+
+    #[test]
+    fn picks_formats_by_name_and_extension() {
+        assert_eq!("yml".parse::<Format>().unwrap(), Format::Yaml);
+        assert_eq!(
+            Format::from_path(Path::new("a/b.json")).unwrap(),
+            Format::Json
+        );
+        let err = Format::from_path(Path::new("b.ini")).unwrap_err();
+        assert!(matches!(err, Error::UnknownFormat { .. }));
+    }
+
+    #[test]
+    fn renders_yaml_non_finite_floats() {
+        let doc: DocumentMut = "a = inf\nb = -inf\nc = [nan]\nd = { e = 1.5 }\n"
+            .parse()
+            .unwrap();
+        let yaml = render_document(&doc, Format::Yaml).unwrap();
+        assert_eq!(yaml, "a: .inf\nb: -.inf\nc:\n- .nan\nd:\n  e: 1.5\n");
+        let back = parse_document(&yaml, Format::Yaml).unwrap();
+        assert_eq!(
+            back.to_string(),
+            "a = inf\nb = -inf\nc = [nan]\n\n[d]\ne = 1.5\n"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! Database system built around TOML files.
+
+pub mod alias;
+pub mod cache;
+pub mod comments;
+pub mod constraints;
+pub mod deprecation;
+pub mod doctor;
+pub mod env;
+mod error;
+pub mod grep;
+pub mod hash;
+pub mod interop;
+pub mod limits;
+pub mod lint;
+pub mod migrations;
+pub mod ops;
+pub mod path;
+pub mod scan;
+pub mod stats;
+pub mod template;
+pub mod validate;
+pub mod walk;
+
+pub use error::{Error, Result};
+pub use path::{KeyPath, KeyPattern, Segment};
+pub use template::RenderScalar;
@@ -0,0 +1,155 @@
+//! Resource limits for documents and values.
+//!
+//! Limits are checked before a document is read into memory, after it is parsed, and by writers
+//! before a value is stored, so oversized input fails early with [`Error::LimitExceeded`].
+
+use crate::path::KeyPath;
+use crate::walk::walk;
+use crate::{Error, Result};
+use std::io::Read;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Size and shape limits enforced on documents and values.
+///
+/// A limit of `None` is not enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum size of a whole document, in bytes.
+    pub max_document_size: Option<usize>,
+    /// Maximum size of a single value stored under a table key, in bytes of TOML.
+    pub max_value_size: Option<usize>,
+    /// Maximum number of keys and indices on the path to any value.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for Limits {
+    /// Limits that comfortably fit hand-written configuration: 16 MiB documents, 1 MiB values
+    /// and a nesting depth of 64.
+    fn default() -> Self {
+        Limits {
+            max_document_size: Some(16 * 1024 * 1024),
+            max_value_size: Some(1024 * 1024),
+            max_depth: Some(64),
+        }
+    }
+}
+
+impl Limits {
+    /// Limits that are never exceeded.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_document_size: None,
+            max_value_size: None,
+            max_depth: None,
+        }
+    }
+
+    /// Reads and parses the document at `path`.
+    ///
+    /// The file size is checked before anything is read.
+    pub fn read(&self, path: &Path) -> Result<DocumentMut> {
+        let file = std::fs::File::open(path).map_err(Error::Io)?;
+        let len = file.metadata().map_err(Error::Io)?.len();
+        check(
+            "document size",
+            self.max_document_size,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            KeyPath::new(),
+        )?;
+        // The file may grow after the size check, so never read more than the limit allows.
+        let cap = self
+            .max_document_size
+            .map_or(u64::MAX, |max| max as u64 + 1);
+        let mut src = String::new();
+        file.take(cap).read_to_string(&mut src).map_err(Error::Io)?;
+        self.parse(&src)
+    }
+
+    /// Parses a document, checking its size before parsing and its shape after.
+    pub fn parse(&self, src: &str) -> Result<DocumentMut> {
+        check(
+            "document size",
+            self.max_document_size,
+            src.len(),
+            KeyPath::new(),
+        )?;
+        let doc = src.parse::<DocumentMut>().map_err(Error::Toml)?;
+        self.check_document(&doc)?;
+        Ok(doc)
+    }
+
+    /// Checks the nesting depth and value sizes of a parsed document.
+    pub fn check_document(&self, doc: &DocumentMut) -> Result<()> {
+        if let Some(max) = self.max_depth {
+            for entry in walk(doc) {
+                check("nesting depth", Some(max), entry.path.len(), entry.path)?;
+            }
+        }
+        if self.max_value_size.is_some() {
+            self.check_values(doc, &KeyPath::new())?;
+        }
+        Ok(())
+    }
+
+    /// Checks a value about to be written at `path`.
+    ///
+    /// The depth checked is that of the deepest value inside `value`.
+    pub fn check_value(&self, path: &KeyPath, value: &Value) -> Result<()> {
+        let depth = path.len() + inner_depth(value);
+        check("nesting depth", self.max_depth, depth, path.clone())?;
+        check(
+            "value size",
+            self.max_value_size,
+            value_size(value),
+            path.clone(),
+        )
+    }
+
+    fn check_values(&self, table: &Table, path: &KeyPath) -> Result<()> {
+        for (key, item) in table.iter() {
+            let path = path.with_key(key);
+            match item {
+                Item::Value(value) => {
+                    check("value size", self.max_value_size, value_size(value), path)?
+                }
+                Item::Table(table) => self.check_values(table, &path)?,
+                Item::ArrayOfTables(array) => {
+                    for (i, table) in array.iter().enumerate() {
+                        self.check_values(table, &path.with_index(i))?;
+                    }
+                }
+                Item::None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn inner_depth(value: &Value) -> usize {
+    match value {
+        Value::Array(array) => array.iter().map(|v| 1 + inner_depth(v)).max(),
+        Value::InlineTable(table) => table.iter().map(|(_, v)| 1 + inner_depth(v)).max(),
+        _ => None,
+    }
+    .unwrap_or(0)
+}
+
+/// Size of the value as written, without surrounding whitespace and comments.
+pub(crate) fn value_size(value: &Value) -> usize {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string().len()
+}
+
+fn check(limit: &'static str, max: Option<usize>, actual: usize, path: KeyPath) -> Result<()> {
+    match max {
+        Some(max) if actual > max => Err(Error::LimitExceeded {
+            limit,
+            max,
+            actual,
+            path,
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,168 @@
+//! Configurable style checks for TOML documents.
+
+use crate::path::KeyPath;
+use crate::walk::{is_metadata, walk, Node};
+use std::fmt;
+use toml_edit::{DocumentMut, Value};
+
+/// A check that can be run by a [`Linter`].
+pub trait LintRule {
+    /// Name used to report findings and to toggle the rule.
+    fn name(&self) -> &'static str;
+
+    /// Checks a single node. `key` is the node's own key, or `None` for array elements.
+    ///
+    /// Returns a message describing the problem, if there is one.
+    fn check(&self, path: &KeyPath, key: Option<&str>, node: Node<'_>) -> Option<String>;
+}
+
+/// A problem reported by a [`LintRule`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub path: KeyPath,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.path, self.message, self.rule)
+    }
+}
+
+/// Runs a set of lint rules over a document.
+pub struct Linter {
+    rules: Vec<(Box<dyn LintRule>, bool)>,
+}
+
+impl Default for Linter {
+    /// Creates a linter with every built-in rule enabled.
+    fn default() -> Self {
+        Linter::empty()
+            .with_rule(NoEmptyTables)
+            .with_rule(SnakeCaseKeys)
+            .with_rule(NoTabsInStrings)
+            .with_rule(HomogeneousArrays)
+    }
+}
+
+impl Linter {
+    /// Creates a linter without any rules.
+    pub fn empty() -> Self {
+        Linter { rules: vec![] }
+    }
+
+    /// Adds an enabled rule.
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.push((Box::new(rule), true));
+        self
+    }
+
+    /// Enables or disables every rule called `name`, returning false if there is none.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for (_, on) in self.rules.iter_mut().filter(|(r, _)| r.name() == name) {
+            *on = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Returns the names of the rules and whether each one is enabled.
+    pub fn rules(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.rules.iter().map(|(rule, on)| (rule.name(), *on))
+    }
+
+    /// Lints a document, returning findings in document order.
+    ///
+    /// Metadata tables and keys, as told by [`is_metadata`], are not linted.
+    pub fn lint(&self, doc: &DocumentMut) -> Vec<Lint> {
+        let mut lints = vec![];
+        for entry in walk(doc).filter(|entry| !is_metadata(&entry.path)) {
+            for (rule, _) in self.rules.iter().filter(|(_, on)| *on) {
+                if let Some(message) = rule.check(&entry.path, entry.key, entry.node) {
+                    lints.push(Lint {
+                        rule: rule.name(),
+                        path: entry.path.clone(),
+                        message,
+                    });
+                }
+            }
+        }
+        lints
+    }
+}
+
+/// Reports tables and inline tables that have no entries.
+pub struct NoEmptyTables;
+
+impl LintRule for NoEmptyTables {
+    fn name(&self) -> &'static str {
+        "no-empty-tables"
+    }
+
+    fn check(&self, _: &KeyPath, _: Option<&str>, node: Node<'_>) -> Option<String> {
+        let empty = match node {
+            Node::Table(table) => table.is_empty() && !table.is_implicit(),
+            Node::Value(Value::InlineTable(table)) => table.is_empty(),
+            _ => false,
+        };
+        empty.then(|| "table is empty".to_string())
+    }
+}
+
+/// Reports keys that are not `snake_case`.
+pub struct SnakeCaseKeys;
+
+impl LintRule for SnakeCaseKeys {
+    fn name(&self) -> &'static str {
+        "snake-case-keys"
+    }
+
+    fn check(&self, _: &KeyPath, key: Option<&str>, _: Node<'_>) -> Option<String> {
+        let key = key?;
+        let snake = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        (!snake).then(|| format!("key `{key}` is not snake_case"))
+    }
+}
+
+/// Reports string values that contain tab characters.
+pub struct NoTabsInStrings;
+
+impl LintRule for NoTabsInStrings {
+    fn name(&self) -> &'static str {
+        "no-tabs-in-strings"
+    }
+
+    fn check(&self, _: &KeyPath, _: Option<&str>, node: Node<'_>) -> Option<String> {
+        match node {
+            Node::Value(Value::String(s)) if s.value().contains('\t') => {
+                Some("string contains a tab".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reports arrays whose elements are not all of the same type.
+pub struct HomogeneousArrays;
+
+impl LintRule for HomogeneousArrays {
+    fn name(&self) -> &'static str {
+        "homogeneous-arrays"
+    }
+
+    fn check(&self, _: &KeyPath, _: Option<&str>, node: Node<'_>) -> Option<String> {
+        let Node::Value(Value::Array(array)) = node else {
+            return None;
+        };
+        let mut types = array.iter().map(Value::type_name);
+        let first = types.next()?;
+        types
+            .find(|t| *t != first)
+            .map(|other| format!("array mixes {first} and {other}"))
+    }
+}
@@ -0,0 +1,130 @@
+//! Ordered, named changes to the layout of a document.
+//!
+//! Applications register their migrations once, in order, and run [`Migrations::up`] whenever a
+//! document is opened. The names of applied migrations are recorded in the document itself, in
+//! a `__migrations` array, so each migration runs once per document:
+//!
+//! ```toml
+//! __migrations = ["rename-listen-to-address", "split-log-table"]
+//! ```
+
+use crate::ops::{insert_at, Position};
+use crate::path::KeyPath;
+use crate::{Error, Result};
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// Name of the key recording the migrations applied to a document.
+pub const KEY: &str = "__migrations";
+
+type Step = Box<dyn Fn(&mut DocumentMut) -> Result<()>>;
+
+/// An ordered list of migrations.
+#[derive(Default)]
+pub struct Migrations {
+    steps: Vec<(String, Step)>,
+}
+
+impl Migrations {
+    /// Creates an empty list of migrations.
+    pub fn new() -> Self {
+        Migrations::default()
+    }
+
+    /// Adds a migration after the ones already added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a migration called `name` was already added.
+    pub fn with_migration(
+        mut self,
+        name: impl Into<String>,
+        migration: impl Fn(&mut DocumentMut) -> Result<()> + 'static,
+    ) -> Self {
+        let name = name.into();
+        assert!(
+            self.steps.iter().all(|(n, _)| *n != name),
+            "duplicate migration `{name}`"
+        );
+        self.steps.push((name, Box::new(migration)));
+        self
+    }
+
+    /// Returns the name of every migration and whether it has been applied to `doc`.
+    pub fn status(&self, doc: &DocumentMut) -> Result<Vec<(&str, bool)>> {
+        let applied = applied(doc)?;
+        Ok(self
+            .steps
+            .iter()
+            .map(|(name, _)| (name.as_str(), applied.contains(name)))
+            .collect())
+    }
+
+    /// Returns the names of the migrations not yet applied to `doc`, in order.
+    pub fn pending(&self, doc: &DocumentMut) -> Result<Vec<&str>> {
+        let status = self.status(doc)?;
+        Ok(status
+            .into_iter()
+            .filter(|(_, applied)| !applied)
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Applies the pending migrations to `doc` in order, returning their names.
+    ///
+    /// If a migration fails, `doc` is left as it was before any of them ran and the failure is
+    /// returned as [`Error::Migration`].
+    pub fn up(&self, doc: &mut DocumentMut) -> Result<Vec<&str>> {
+        let applied = applied(doc)?;
+        let mut work = doc.clone();
+        let mut ran = vec![];
+        for (name, migration) in &self.steps {
+            if applied.contains(name) {
+                continue;
+            }
+            migration(&mut work).map_err(|source| Error::Migration {
+                name: name.clone(),
+                source: Box::new(source),
+            })?;
+            record(&mut work, name)?;
+            ran.push(name.as_str());
+        }
+        *doc = work;
+        Ok(ran)
+    }
+}
+
+/// Returns the names of the migrations recorded as applied to `doc`.
+pub fn applied(doc: &DocumentMut) -> Result<Vec<String>> {
+    let Some(item) = doc.get(KEY) else {
+        return Ok(vec![]);
+    };
+    let names = item.as_array().and_then(|array| {
+        array
+            .iter()
+            .map(|value| value.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+    });
+    names.ok_or_else(|| Error::TypeMismatch {
+        path: KeyPath::new().with_key(KEY),
+        expected: "an array of strings",
+        found: item.type_name(),
+    })
+}
+
+fn record(doc: &mut DocumentMut, name: &str) -> Result<()> {
+    match doc.get_mut(KEY) {
+        Some(Item::Value(Value::Array(array))) => array.push(name),
+        Some(item) => {
+            return Err(Error::TypeMismatch {
+                path: KeyPath::new().with_key(KEY),
+                expected: "an array of strings",
+                found: item.type_name(),
+            })
+        }
+        None => {
+            let array = Array::from_iter([name]);
+            insert_at(doc, KEY, Item::Value(array.into()), Position::First)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1652 @@
+//! Maintenance operations on TOML values.
+
+use crate::path::{KeyPath, KeyPattern, Segment};
+use crate::walk::{get, get_mut, is_metadata, walk, Node, NodeMut};
+use crate::{Error, Result};
+use std::cmp::Ordering;
+use toml_edit::{Array, ArrayOfTables, Item, RawString, Table, TableLike, Value};
+
+/// Sorts an array by value.
+///
+/// The sort is stable. Values of different types are grouped by type, in the order
+/// strings, integers, floats, booleans, datetimes, arrays, inline tables. The whitespace layout
+/// stays as it was, and a comment at the end of an element's line moves with the element, while
+/// comments on lines of their own stay in place.
+pub fn sort_array(array: &mut Array) {
+    let values: Vec<&Value> = array.iter().collect();
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| compare(values[a], values[b]));
+    rearrange(array, &order);
+}
+
+/// Sorts an array of inline tables by the value of `field`.
+///
+/// The sort is stable. Elements that are not inline tables, or that do not have `field`, are
+/// moved after the elements that do, keeping their relative order. Layout and comments are kept
+/// as by [`sort_array`].
+pub fn sort_array_by_field(array: &mut Array, field: &str) {
+    let values: Vec<&Value> = array.iter().collect();
+    let key = |i: usize| values[i].as_inline_table().and_then(|t| t.get(field));
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => compare(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    rearrange(array, &order);
+}
+
+/// Removes every element that is equal to an earlier element of the array.
+///
+/// A comment at the end of a removed element's line is removed with it. Returns the number of
+/// elements that were removed.
+pub fn dedupe_array(array: &mut Array) -> usize {
+    let values: Vec<&Value> = array.iter().collect();
+    let before = values.len();
+    let mut order: Vec<usize> = Vec::with_capacity(before);
+    for (i, value) in values.iter().enumerate() {
+        if !order.iter().any(|&kept| value_eq(values[kept], value)) {
+            order.push(i);
+        }
+    }
+    rearrange(array, &order);
+    before - array.len()
+}
+
+/// Appends `value` to the array unless an equal element is already there, returning whether it
+/// was appended.
+///
+/// Elements are compared with [`value_eq`], so `0x10` is not appended to an array holding `16`.
+pub fn append_unique(array: &mut Array, value: Value) -> bool {
+    if array_contains(array, &value) {
+        return false;
+    }
+    insert_element(array, array.len(), value);
+    true
+}
+
+/// Sets the value at `path` below `table`, descending through tables, inline tables, arrays and
+/// arrays of tables.
+///
+/// Only the addressed key or element is written, so a single field of an inline table can be
+/// updated without rewriting the rest of it. Everything but the last segment must already exist,
+/// and a trailing index must address an existing array element. When a value is replaced, its
+/// decor is carried over to `value`. Returns the previous item, if any.
+pub fn set_field(table: &mut Table, path: &KeyPath, value: Value) -> Result<Option<Item>> {
+    let (current, last) = parent_container(table, path)?;
+    let mut value = value;
+    match (current, last) {
+        (Container::Table(table), Segment::Key(key)) => {
+            if let Some(existing) = table.get(key).and_then(Item::as_value) {
+                *value.decor_mut() = existing.decor().clone();
+            }
+            Ok(table.insert(key, Item::Value(value)))
+        }
+        (Container::Array(array), Segment::Index(i)) => {
+            let existing = array
+                .get(*i)
+                .ok_or_else(|| Error::MissingKey { path: path.clone() })?;
+            *value.decor_mut() = existing.decor().clone();
+            Ok(Some(Item::Value(array.replace_formatted(*i, value))))
+        }
+        (container, segment) => Err(container.mismatch(segment, path.clone())),
+    }
+}
+
+/// Writes `value` at `path` below `table` unless something is already there, returning whether
+/// it was written.
+///
+/// The value is written with [`set_field`], so everything but the last segment must exist and a
+/// trailing index must address an existing element.
+pub fn set_default(table: &mut Table, path: &KeyPath, value: Value) -> Result<bool> {
+    if get(table, path).is_some() {
+        return Ok(false);
+    }
+    set_field(table, path, value)?;
+    Ok(true)
+}
+
+/// Adds `delta` to the integer or float at `path` below `table`, returning the new value.
+///
+/// The value is written with [`set_field`], so its comments are kept, and an integer keeps the syntax it was written in, such as
+/// hexadecimal, unless the result is negative and can only be written in decimal. Fails with
+/// [`Error::MissingKey`] if there is no value at `path`, [`Error::TypeMismatch`] if it is not a
+/// number, and [`Error::Unrepresentable`] if an integer would overflow, leaving `table`
+/// unchanged.
+pub fn increment(table: &mut Table, path: &KeyPath, delta: i64) -> Result<Value> {
+    let node = get(table, path).ok_or_else(|| Error::MissingKey { path: path.clone() })?;
+    let value = match node {
+        Node::Value(Value::Integer(i)) => {
+            let sum = i.value().checked_add(delta).ok_or(Error::Unrepresentable {
+                path: path.clone(),
+                what: "integer overflow",
+            })?;
+            let raw = i.as_repr().and_then(|repr| repr.as_raw().as_str());
+            integer_value(sum, raw.map_or(IntFormat::Decimal, int_format))
+                .unwrap_or_else(|_| Value::from(sum))
+        }
+        Node::Value(Value::Float(f)) => Value::from(f.value() + delta as f64),
+        node => {
+            return Err(Error::TypeMismatch {
+                path: path.clone(),
+                expected: "an integer or float",
+                found: node.type_name(),
+            })
+        }
+    };
+    set_field(table, path, value.clone())?;
+    Ok(value)
+}
+
+/// Removes the key or element at `path` below `table`, returning it, or `None` if there was no
+/// such key.
+///
+/// Everything but the last segment must exist, as for [`set_field`], and a trailing index must
+/// address an existing element, or [`Error::MissingKey`] is returned. Removing an element from
+/// an array keeps the whitespace layout of the array.
+pub fn remove_field(table: &mut Table, path: &KeyPath) -> Result<Option<Item>> {
+    let (current, last) = parent_container(table, path)?;
+    match (current, last) {
+        (Container::Table(table), Segment::Key(key)) => Ok(table.remove(key)),
+        (Container::Array(array), Segment::Index(i)) if *i < array.len() => {
+            let removed = array.get(*i).cloned();
+            let order: Vec<usize> = (0..array.len()).filter(|j| j != i).collect();
+            rearrange(array, &order);
+            Ok(removed.map(Item::Value))
+        }
+        (Container::ArrayOfTables(array), Segment::Index(i)) if *i < array.len() => {
+            Ok(Some(Item::Table(array.remove(*i))))
+        }
+        (Container::Array(_) | Container::ArrayOfTables(_), Segment::Index(_)) => {
+            Err(Error::MissingKey { path: path.clone() })
+        }
+        (container, segment) => Err(container.mismatch(segment, path.clone())),
+    }
+}
+
+/// Inserts `value` into an array at the index `path` ends with, shifting later elements back.
+///
+/// The index may be the length of the array, which appends. The new element is laid out like
+/// its neighbours. Everything but the last segment must exist, as for [`set_field`]. Fails with
+/// [`Error::MissingKey`] for an index past the end.
+pub fn insert_at_index(table: &mut Table, path: &KeyPath, value: Value) -> Result<()> {
+    let (current, last) = parent_container(table, path)?;
+    match (current, last) {
+        (Container::Array(array), Segment::Index(i)) if *i <= array.len() => {
+            insert_element(array, *i, value);
+            Ok(())
+        }
+        (Container::Array(_), Segment::Index(_)) => Err(Error::MissingKey { path: path.clone() }),
+        (container, segment) => Err(container.mismatch(segment, path.clone())),
+    }
+}
+
+/// Inserts `value` at `i`, laid out like its neighbours. An element moved from the front keeps
+/// any comment above it there, with the new first element.
+fn insert_element(array: &mut Array, i: usize, mut value: Value) {
+    let prefix = |array: &Array, j: usize| -> String {
+        let prefix = array.get(j).and_then(|v| v.decor().prefix());
+        prefix
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    value.decor_mut().clear();
+    if array.is_empty() {
+        array.push(value);
+        return;
+    }
+    if i == 0 {
+        let first = prefix(array, 0);
+        value.decor_mut().set_prefix(first.clone());
+        array.insert_formatted(0, value);
+        array
+            .get_mut(1)
+            .expect("array has a second element")
+            .decor_mut()
+            .set_prefix(separator(&first));
+        return;
+    }
+    let neighbour = prefix(array, i.min(array.len() - 1));
+    value.decor_mut().set_prefix(separator(&neighbour));
+    if i == array.len() {
+        // Whitespace before the closing bracket follows the last element.
+        let last = array.get_mut(i - 1).expect("array is not empty");
+        let suffix = last
+            .decor()
+            .suffix()
+            .and_then(|s| s.as_str())
+            .unwrap_or_default()
+            .to_string();
+        last.decor_mut().set_suffix("");
+        value.decor_mut().set_suffix(suffix);
+    }
+    array.insert_formatted(i, value);
+}
+
+/// The whitespace that separates an element from the one before it, based on the prefix of an
+/// element in the same array: a line break and indentation in multi-line arrays, a space
+/// otherwise.
+fn separator(prefix: &str) -> String {
+    match prefix.rfind('\n') {
+        Some(end) => format!("\n{}", &prefix[end + 1..]),
+        None => " ".to_string(),
+    }
+}
+
+/// Returns whether the array has an element equal to `value`, compared with [`value_eq`].
+pub fn array_contains(array: &Array, value: &Value) -> bool {
+    array.iter().any(|existing| value_eq(existing, value))
+}
+
+/// Descends to the container holding the last segment of `path`, returning it with that segment.
+fn parent_container<'a, 'p>(
+    table: &'a mut Table,
+    path: &'p KeyPath,
+) -> Result<(Container<'a>, &'p Segment)> {
+    let Some((last, parents)) = path.segments().split_last() else {
+        return Err(Error::EmptyPath);
+    };
+    let mut at = KeyPath::new();
+    let mut current = Container::Table(table);
+    for segment in parents {
+        at.push(segment.clone());
+        current = current.child(segment, &at)?;
+    }
+    Ok((current, last))
+}
+
+/// Returns the paths below `table` that match `pattern`, in document order.
+pub fn find_matching(table: &Table, pattern: &KeyPattern) -> Vec<KeyPath> {
+    walk(table)
+        .map(|entry| entry.path)
+        .filter(|path| pattern.matches(path))
+        .collect()
+}
+
+/// Removes every key and element below `table` that matches `pattern`, returning their paths as
+/// [`find_matching`] does.
+///
+/// Several elements of one array are removed from the back, so the returned indices are those
+/// the elements had before anything was removed.
+pub fn remove_matching(table: &mut Table, pattern: &KeyPattern) -> Vec<KeyPath> {
+    let paths = find_matching(table, pattern);
+    for path in paths.iter().rev() {
+        remove_field(table, path).expect("matched paths exist");
+    }
+    paths
+}
+
+/// Removes every key of the table at `path` below `table`, returning the table as it was before.
+///
+/// With `keep_header`, a standard table keeps its header and the comments around it, and is
+/// written as an empty table. Otherwise the header goes too, unless the table is the root. Inline
+/// tables are emptied either way. Fails with [`Error::MissingKey`] if there is nothing at `path`
+/// and [`Error::TypeMismatch`] if it is not a table.
+pub fn clear_table(table: &mut Table, path: &KeyPath, keep_header: bool) -> Result<Item> {
+    let node = get_mut(table, path).ok_or_else(|| Error::MissingKey { path: path.clone() })?;
+    match node {
+        NodeMut::Table(table) => {
+            let before = table.clone();
+            table.clear();
+            if !path.is_empty() {
+                table.set_implicit(!keep_header);
+                if !keep_header {
+                    table.decor_mut().clear();
+                }
+            }
+            Ok(Item::Table(before))
+        }
+        NodeMut::Value(Value::InlineTable(table)) => {
+            let before = table.clone();
+            table.clear();
+            Ok(Item::Value(Value::InlineTable(before)))
+        }
+        node => Err(Error::TypeMismatch {
+            path: path.clone(),
+            expected: "a table",
+            found: node.type_name(),
+        }),
+    }
+}
+
+/// Removes every empty table below `table`, standard or inline, including tables that only held
+/// empty tables. Returns the paths of the removed tables that were not inside another removed
+/// table, in document order.
+///
+/// Tables in arrays of tables are pruned, but never removed themselves, so that the indices of
+/// their siblings do not change.
+pub fn prune_empty(table: &mut Table) -> Vec<KeyPath> {
+    let mut removed = vec![];
+    prune(table, &KeyPath::new(), &mut removed);
+    removed
+}
+
+fn prune(table: &mut dyn TableLike, path: &KeyPath, removed: &mut Vec<KeyPath>) {
+    let mut empty = vec![];
+    for (key, item) in table.iter_mut() {
+        let path = path.with_key(key.get());
+        match item {
+            Item::ArrayOfTables(array) => {
+                for (i, table) in array.iter_mut().enumerate() {
+                    prune(table, &path.with_index(i), removed);
+                }
+            }
+            item => {
+                if let Some(table) = item.as_table_like_mut() {
+                    let before = removed.len();
+                    prune(table, &path, removed);
+                    if table.is_empty() {
+                        // The removed children are covered by this table.
+                        removed.truncate(before);
+                        empty.push(key.get().to_string());
+                        removed.push(path);
+                    }
+                }
+            }
+        }
+    }
+    for key in empty {
+        table.remove(&key);
+    }
+}
+
+/// Removes the tables on `path` that are empty, starting from its parent and stopping at the
+/// first one that is not, which is what is left behind by removing the key at `path`. Returns the
+/// path of the outermost removed table.
+///
+/// Tables in arrays of tables and the root table are never removed.
+pub fn prune_parents(table: &mut Table, path: &KeyPath) -> Option<KeyPath> {
+    let mut pruned = None;
+    let mut parent = path.parent()?;
+    while let Some(Segment::Key(_)) = parent.last() {
+        let empty = get(table, &parent).is_some_and(|node| match node {
+            Node::Table(table) => table.is_empty(),
+            Node::Value(Value::InlineTable(table)) => table.is_empty(),
+            _ => false,
+        });
+        if !empty {
+            break;
+        }
+        remove_field(table, &parent).expect("parent exists");
+        pruned = Some(parent.clone());
+        parent = parent.parent()?;
+    }
+    pruned
+}
+
+/// Replaces the contents of the table at `path` below `table` with those of `contents`, returning
+/// the table as it was before.
+///
+/// `contents` must be a table or inline table, such as the root of a parsed TOML fragment. The
+/// table keeps its own kind, header and comments, and only its entries are replaced. Fails with
+/// [`Error::MissingKey`] if there is nothing at `path` and [`Error::TypeMismatch`] if it is not a
+/// table, or with an empty path if `contents` is not. `table` is left unchanged on failure.
+pub fn replace_table(table: &mut Table, path: &KeyPath, contents: Item) -> Result<Item> {
+    let mut contents = match contents {
+        Item::Table(table) => table,
+        Item::Value(Value::InlineTable(table)) => table.into_table(),
+        other => {
+            return Err(Error::TypeMismatch {
+                path: KeyPath::new(),
+                expected: "a table",
+                found: other.type_name(),
+            })
+        }
+    };
+    for (_, item) in contents.iter_mut() {
+        forget_positions(item);
+    }
+    let node = get_mut(table, path).ok_or_else(|| Error::MissingKey { path: path.clone() })?;
+    match node {
+        NodeMut::Table(table) => {
+            contents.set_position(table.position());
+            contents.set_implicit(table.is_implicit());
+            contents.set_dotted(table.is_dotted());
+            *contents.decor_mut() = table.decor().clone();
+            Ok(Item::Table(std::mem::replace(table, contents)))
+        }
+        NodeMut::Value(Value::InlineTable(table)) => {
+            let mut contents = contents.into_inline_table();
+            *contents.decor_mut() = table.decor().clone();
+            contents.fmt();
+            Ok(Item::Value(Value::InlineTable(std::mem::replace(
+                table, contents,
+            ))))
+        }
+        node => Err(Error::TypeMismatch {
+            path: path.clone(),
+            expected: "a table",
+            found: node.type_name(),
+        }),
+    }
+}
+
+/// Adds every key of `template` that `table` lacks, leaving the values already there untouched.
+///
+/// Tables present in both, standard or inline, are filled recursively. A key whose value has a
+/// different type than in the template is left as it is, and arrays are never merged element by
+/// element. Added keys keep their comments from the template, and added tables are written after
+/// the tables already there. Inline tables that gain keys are reformatted. Returns the paths of
+/// the added keys.
+pub fn fill_defaults(table: &mut Table, template: &Table) -> Vec<KeyPath> {
+    let mut added = vec![];
+    fill(table, template, &KeyPath::new(), &mut added);
+    added
+}
+
+fn fill(
+    table: &mut dyn TableLike,
+    template: &dyn TableLike,
+    path: &KeyPath,
+    added: &mut Vec<KeyPath>,
+) {
+    for (key, default) in template.iter() {
+        let path = path.with_key(key);
+        match table.get_mut(key) {
+            Some(item) => {
+                let Some(template) = default.as_table_like() else {
+                    continue;
+                };
+                let before = added.len();
+                match item {
+                    Item::Value(Value::InlineTable(table)) => {
+                        fill(table, template, &path, added);
+                        // Appended entries would otherwise follow the old last entry's padding.
+                        if added.len() > before {
+                            table.fmt();
+                        }
+                    }
+                    item => {
+                        if let Some(table) = item.as_table_like_mut() {
+                            fill(table, template, &path, added);
+                        }
+                    }
+                }
+            }
+            None => {
+                let mut item = default.clone();
+                forget_positions(&mut item);
+                match template.key(key) {
+                    // Carries the comments above the key along with it.
+                    Some(key) => table.entry_format(key).or_insert(item),
+                    None => table.entry(key).or_insert(item),
+                };
+                added.push(path);
+            }
+        }
+    }
+}
+
+/// How a table differs from a template, as reported by [`drift`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Drift {
+    /// Keys present in both whose values differ.
+    pub changed: Vec<KeyPath>,
+    /// Keys of the template that the table lacks.
+    pub missing: Vec<KeyPath>,
+    /// Keys of the table that the template lacks.
+    pub extra: Vec<KeyPath>,
+}
+
+impl Drift {
+    /// Returns whether the table matches the template.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Compares `table` against `template`, reporting the keys that differ, that are missing and
+/// that are extra.
+///
+/// Tables present in both, standard or inline, are compared key by key. Anything else is compared
+/// with [`toml_eq`], so formatting does not count as a change and arrays are compared as a whole.
+/// A missing or extra table is reported once, by its own path. Metadata, as told by
+/// [`is_metadata`], is not compared.
+pub fn drift(table: &Table, template: &Table) -> Drift {
+    let mut drift = Drift::default();
+    compare_tables(table, template, &KeyPath::new(), &mut drift);
+    drift
+}
+
+fn compare_tables(
+    table: &dyn TableLike,
+    template: &dyn TableLike,
+    path: &KeyPath,
+    drift: &mut Drift,
+) {
+    for (key, default) in template.iter() {
+        let path = path.with_key(key);
+        if is_metadata(&path) {
+            continue;
+        }
+        let Some(item) = table.get(key) else {
+            drift.missing.push(path);
+            continue;
+        };
+        match (item.as_table_like(), default.as_table_like()) {
+            (Some(table), Some(template)) => compare_tables(table, template, &path, drift),
+            _ if !toml_eq(item, default) => drift.changed.push(path),
+            _ => {}
+        }
+    }
+    for (key, _) in table.iter() {
+        let path = path.with_key(key);
+        if !template.contains_key(key) && !is_metadata(&path) {
+            drift.extra.push(path);
+        }
+    }
+}
+
+/// Clears the document positions of the tables in `item`, which refer to the document it came
+/// from.
+fn forget_positions(item: &mut Item) {
+    let tables: Vec<&mut Table> = match item {
+        Item::Table(table) => vec![table],
+        Item::ArrayOfTables(array) => array.iter_mut().collect(),
+        _ => vec![],
+    };
+    for table in tables {
+        table.set_position(None);
+        for (_, item) in table.iter_mut() {
+            forget_positions(item);
+        }
+    }
+}
+
+/// Where [`insert_at`] places a new key among the existing keys of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// Before every other key.
+    First,
+    /// After every other key.
+    Last,
+    /// Directly after the given key.
+    After(String),
+    /// Before the first key that sorts after it, which keeps a sorted table sorted.
+    Sorted,
+}
+
+/// Inserts `item` under `key`, placing a new key at `position`.
+///
+/// Replacing an existing key keeps it where it is, and a replaced value's decor is carried over as
+/// in [`set_field`]. Subtables are written after the plain keys of
+/// a table regardless of their position, so the position only matters among plain keys. Fails
+/// with [`Error::MissingKey`] if the key to insert after does not exist. Returns the previous
+/// item, if any.
+pub fn insert_at(
+    table: &mut Table,
+    key: &str,
+    item: Item,
+    position: Position,
+) -> Result<Option<Item>> {
+    if let Some(existing) = table.get(key) {
+        let mut item = item;
+        if let (Some(existing), Item::Value(value)) = (existing.as_value(), &mut item) {
+            *value.decor_mut() = existing.decor().clone();
+        }
+        return Ok(table.insert(key, item));
+    }
+    let mut order: Vec<String> = table.iter().map(|(k, _)| k.to_string()).collect();
+    let index = match &position {
+        Position::First => 0,
+        Position::Last => order.len(),
+        Position::After(anchor) => {
+            let i = order.iter().position(|k| k == anchor);
+            i.ok_or_else(|| Error::MissingKey {
+                path: KeyPath::new().with_key(anchor.as_str()),
+            })? + 1
+        }
+        Position::Sorted => order
+            .iter()
+            .position(|k| k.as_str() > key)
+            .unwrap_or(order.len()),
+    };
+    table.insert(key, item);
+    // Moves the keys that belong after the new one behind it. Only top-level entries are
+    // reinserted, so the keys inside dotted tables keep their order.
+    for moved in order.drain(index..) {
+        if let Some((moved, item)) = table.remove_entry(&moved) {
+            table.insert_formatted(&moved, item);
+        }
+    }
+    Ok(None)
+}
+
+/// Sorts the plain keys of a table by name.
+///
+/// Comments and whitespace attached to a key move with it. Subtables are not reordered.
+pub fn sort_table(table: &mut Table) {
+    table.sort_values();
+}
+
+/// The syntax a string value is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringKind {
+    /// `"..."`, with escapes.
+    #[default]
+    Basic,
+    /// `'...'`, written exactly as given.
+    Literal,
+    /// `"""..."""` over several lines, with escapes.
+    MultilineBasic,
+    /// `'''...'''` over several lines, written exactly as given.
+    MultilineLiteral,
+}
+
+/// Creates a string value written in the given syntax.
+///
+/// Multi-line strings start on the line after the opening delimiter. Fails with
+/// [`Error::Unrepresentable`] if `s` cannot be written as a literal string of the requested
+/// kind; basic strings can hold anything.
+pub fn string_value(s: &str, kind: StringKind) -> Result<Value> {
+    let unrepresentable = |what| Error::Unrepresentable {
+        path: KeyPath::new(),
+        what,
+    };
+    let raw = match kind {
+        StringKind::Basic => format!("\"{}\"", escape(s, false)),
+        StringKind::MultilineBasic => format!("\"\"\"\n{}\"\"\"", escape(s, true)),
+        StringKind::Literal => {
+            if s.contains('\'') {
+                return Err(unrepresentable("`'` in a literal string"));
+            }
+            if s.chars().any(|c| c.is_control() && c != '\t') {
+                return Err(unrepresentable("control character in a literal string"));
+            }
+            format!("'{s}'")
+        }
+        StringKind::MultilineLiteral => {
+            if s.contains("'''") {
+                return Err(unrepresentable("`'''` in a multi-line literal string"));
+            }
+            let bare_cr = s
+                .match_indices('\r')
+                .any(|(i, _)| !s[i..].starts_with("\r\n"));
+            if bare_cr
+                || s.chars()
+                    .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+            {
+                return Err(unrepresentable(
+                    "control character in a multi-line literal string",
+                ));
+            }
+            format!("'''\n{s}'''")
+        }
+    };
+    Ok(raw.parse().expect("string value is valid TOML"))
+}
+
+/// Escapes `s` for a basic string. In multi-line strings, newlines and tabs are kept and only
+/// every third consecutive quote is escaped, which is enough to never close the string.
+fn escape(s: &str, multiline: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut quotes = 0;
+    for c in s.chars() {
+        match c {
+            '"' if !multiline || quotes == 2 => {
+                out.push_str("\\\"");
+                quotes = 0;
+                continue;
+            }
+            '"' => {
+                out.push('"');
+                quotes += 1;
+                continue;
+            }
+            '\\' => out.push_str("\\\\"),
+            '\n' | '\t' if multiline => out.push(c),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+        quotes = 0;
+    }
+    out
+}
+
+/// The syntax an integer value is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntFormat {
+    /// `1000000`
+    #[default]
+    Decimal,
+    /// `1_000_000`, with an underscore between groups of three digits.
+    Grouped,
+    /// `0xf4240`
+    Hex,
+    /// `0o3641100`
+    Octal,
+    /// `0b11110100001001000000`
+    Binary,
+}
+
+/// Creates an integer value written in the given syntax.
+///
+/// Fails with [`Error::Unrepresentable`] for a negative integer in hexadecimal, octal or binary,
+/// which TOML only allows for non-negative integers.
+pub fn integer_value(i: i64, format: IntFormat) -> Result<Value> {
+    if i < 0 && !matches!(format, IntFormat::Decimal | IntFormat::Grouped) {
+        return Err(Error::Unrepresentable {
+            path: KeyPath::new(),
+            what: "negative integer in a non-decimal form",
+        });
+    }
+    let raw = match format {
+        IntFormat::Decimal => i.to_string(),
+        IntFormat::Grouped => {
+            let digits = i.unsigned_abs().to_string();
+            let mut raw = String::from(if i < 0 { "-" } else { "" });
+            for (n, c) in digits.chars().enumerate() {
+                if n > 0 && (digits.len() - n).is_multiple_of(3) {
+                    raw.push('_');
+                }
+                raw.push(c);
+            }
+            raw
+        }
+        IntFormat::Hex => format!("{i:#x}"),
+        IntFormat::Octal => format!("0o{i:o}"),
+        IntFormat::Binary => format!("{i:#b}"),
+    };
+    Ok(raw.parse().expect("integer value is valid TOML"))
+}
+
+/// Returns the syntax an integer is written in, from its raw TOML.
+fn int_format(raw: &str) -> IntFormat {
+    match raw.get(..2) {
+        Some("0x") => IntFormat::Hex,
+        Some("0o") => IntFormat::Octal,
+        Some("0b") => IntFormat::Binary,
+        _ if raw.contains('_') => IntFormat::Grouped,
+        _ => IntFormat::Decimal,
+    }
+}
+
+/// Parses an integer written in any TOML syntax, such as `0x1F`, `0o755`, `0b1010` or
+/// `1_000_000`, keeping the syntax it was written in.
+///
+/// Fails with the parser's error if `input` is not a TOML value, and with
+/// [`Error::TypeMismatch`] if it is a value of another type.
+pub fn parse_integer(input: &str) -> Result<Value> {
+    let mut value: Value = input.trim().parse().map_err(Error::Toml)?;
+    if !value.is_integer() {
+        return Err(Error::TypeMismatch {
+            path: KeyPath::new(),
+            expected: "an integer",
+            found: value.type_name(),
+        });
+    }
+    value.decor_mut().clear();
+    Ok(value)
+}
+
+/// Fails with [`Error::Unrepresentable`] if `value` is or contains `nan`, `inf` or `-inf`, for
+/// writers to stores whose readers cannot handle non-finite floats. `path` is where `value` is
+/// about to be written, and the error names the path of the offending float.
+pub fn reject_non_finite(path: &KeyPath, value: &Value) -> Result<()> {
+    match value {
+        Value::Float(f) if !f.value().is_finite() => Err(Error::Unrepresentable {
+            path: path.clone(),
+            what: "non-finite float",
+        }),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, value)| reject_non_finite(&path.with_index(i), value)),
+        Value::InlineTable(table) => table
+            .iter()
+            .try_for_each(|(key, value)| reject_non_finite(&path.with_key(key), value)),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the paths of the `nan`, `inf` and `-inf` floats below `table`, in document order.
+pub fn non_finite_floats(table: &Table) -> Vec<KeyPath> {
+    walk(table)
+        .filter(
+            |entry| matches!(entry.node, Node::Value(Value::Float(f)) if !f.value().is_finite()),
+        )
+        .map(|entry| entry.path)
+        .collect()
+}
+
+/// The syntax a float value is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// The form toml_edit writes floats in.
+    #[default]
+    Default,
+    /// `1500000.0`, never with an exponent.
+    Plain,
+    /// `1.5e6`
+    Exponent,
+}
+
+/// Creates a float value written in the given syntax.
+///
+/// Whatever the format, the written float reads back as exactly `f`. `nan`, `inf` and `-inf`
+/// have only one form.
+pub fn float_value(f: f64, format: FloatFormat) -> Value {
+    if !f.is_finite() {
+        return Value::from(f);
+    }
+    let raw = match format {
+        FloatFormat::Default => return Value::from(f),
+        FloatFormat::Plain => {
+            let raw = f.to_string();
+            if raw.contains('.') {
+                raw
+            } else {
+                raw + ".0"
+            }
+        }
+        FloatFormat::Exponent => format!("{f:e}"),
+    };
+    raw.parse().expect("float value is valid TOML")
+}
+
+/// A value on a key path that can be descended into.
+enum Container<'a> {
+    Table(&'a mut dyn TableLike),
+    Array(&'a mut Array),
+    ArrayOfTables(&'a mut ArrayOfTables),
+}
+
+impl<'a> Container<'a> {
+    /// Descends to `segment`, where `at` is the path up to and including it.
+    fn child(self, segment: &Segment, at: &KeyPath) -> Result<Container<'a>> {
+        let missing = || Error::MissingKey { path: at.clone() };
+        let mismatch = |found| Error::TypeMismatch {
+            path: at.clone(),
+            expected: "a table or array",
+            found,
+        };
+        match (self, segment) {
+            (Container::Table(table), Segment::Key(key)) => {
+                match table.get_mut(key).ok_or_else(missing)? {
+                    Item::ArrayOfTables(array) => Ok(Container::ArrayOfTables(array)),
+                    Item::Value(Value::Array(array)) => Ok(Container::Array(array)),
+                    item => {
+                        let found = item.type_name();
+                        item.as_table_like_mut()
+                            .map(Container::Table)
+                            .ok_or_else(|| mismatch(found))
+                    }
+                }
+            }
+            (Container::Array(array), Segment::Index(i)) => {
+                match array.get_mut(*i).ok_or_else(missing)? {
+                    Value::Array(array) => Ok(Container::Array(array)),
+                    Value::InlineTable(table) => Ok(Container::Table(table)),
+                    value => Err(mismatch(value.type_name())),
+                }
+            }
+            (Container::ArrayOfTables(array), Segment::Index(i)) => {
+                let table = array.get_mut(*i).ok_or_else(missing)?;
+                Ok(Container::Table(table))
+            }
+            (container, segment) => Err(container.mismatch(segment, at.clone())),
+        }
+    }
+
+    /// Error for a segment that does not fit this container, where `at` ends with `segment`.
+    fn mismatch(&self, segment: &Segment, at: KeyPath) -> Error {
+        let found = match self {
+            Container::Table(_) => "table",
+            Container::Array(_) => "array",
+            Container::ArrayOfTables(_) => "array of tables",
+        };
+        let expected = match segment {
+            Segment::Key(_) => "a table",
+            Segment::Index(_) => "an array",
+        };
+        Error::TypeMismatch {
+            path: at.parent().unwrap_or_default(),
+            expected,
+            found,
+        }
+    }
+}
+
+/// Compares two values structurally, ignoring formatting.
+///
+/// This is a total order: values of different types are ordered by type, floats are ordered with
+/// [`f64::total_cmp`], arrays element-wise and inline tables by their sorted entries. The sign of
+/// a NaN is not significant in TOML, so `nan`, `+nan` and `-nan` are all equal, and greater than
+/// every other float.
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value().cmp(b.value()),
+        (Value::Integer(a), Value::Integer(b)) => a.value().cmp(b.value()),
+        (Value::Float(a), Value::Float(b)) => {
+            canonical_nan(*a.value()).total_cmp(&canonical_nan(*b.value()))
+        }
+        (Value::Boolean(a), Value::Boolean(b)) => a.value().cmp(b.value()),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value().cmp(b.value()),
+        (Value::Array(a), Value::Array(b)) => compare_seq(a.iter(), b.iter(), compare),
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            let mut a: Vec<_> = a.iter().collect();
+            let mut b: Vec<_> = b.iter().collect();
+            a.sort_by(|x, y| x.0.cmp(y.0));
+            b.sort_by(|x, y| x.0.cmp(y.0));
+            compare_seq(a.into_iter(), b.into_iter(), |(ka, va), (kb, vb)| {
+                ka.cmp(kb).then_with(|| compare(va, vb))
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Returns whether two values are equal, ignoring formatting.
+///
+/// `3.0` equals `3.00` and `0x10` equals `16`, and inline tables are equal whatever their key
+/// order. Values of different types are never equal, so `1` does not equal `1.0`. Unlike `==` on
+/// floats, `nan` equals `nan`, so a document always equals itself.
+pub fn value_eq(a: &Value, b: &Value) -> bool {
+    compare(a, b) == Ordering::Equal
+}
+
+/// Returns whether two items hold the same data, ignoring formatting.
+///
+/// Besides what [`value_eq`] ignores, a standard table equals an inline table with the same
+/// entries, and an array of tables equals an array of such inline tables.
+pub fn toml_eq(a: &Item, b: &Item) -> bool {
+    match (a, b) {
+        (Item::None, Item::None) => true,
+        (Item::Value(a), Item::Value(b)) => value_eq(a, b),
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| tables_eq(a, b))
+        }
+        (Item::ArrayOfTables(tables), Item::Value(Value::Array(array)))
+        | (Item::Value(Value::Array(array)), Item::ArrayOfTables(tables)) => {
+            tables.len() == array.len()
+                && tables
+                    .iter()
+                    .zip(array.iter())
+                    .all(|(a, b)| b.as_inline_table().is_some_and(|b| tables_eq(a, b)))
+        }
+        _ => match (a.as_table_like(), b.as_table_like()) {
+            (Some(a), Some(b)) => tables_eq(a, b),
+            _ => false,
+        },
+    }
+}
+
+fn tables_eq(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, item)| b.get(key).is_some_and(|other| toml_eq(item, other)))
+}
+
+fn compare_seq<T>(
+    mut a: impl Iterator<Item = T>,
+    mut b: impl Iterator<Item = T>,
+    mut cmp: impl FnMut(T, T) -> Ordering,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) => match cmp(x, y) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+fn canonical_nan(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else {
+        f
+    }
+}
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::String(_) => 0,
+        Value::Integer(_) => 1,
+        Value::Float(_) => 2,
+        Value::Boolean(_) => 3,
+        Value::Datetime(_) => 4,
+        Value::Array(_) => 5,
+        Value::InlineTable(_) => 6,
+    }
+}
+
+/// Rebuilds the array from the elements at the indices in `order`, in that order.
+///
+/// The whitespace layout stays by position, so that reordering or removing elements does not
+/// disturb it, and the last element keeps the whitespace before the closing bracket. A comment ending an element's line moves with the element. It is written at the
+/// start of the next element's prefix or of the array's trailing whitespace, or in the suffix of
+/// the last element when the array has no trailing comma.
+fn rearrange(array: &mut Array, order: &[usize]) {
+    let raw = |s: Option<&RawString>| s.and_then(|s| s.as_str()).map(str::to_string);
+    let values: Vec<Value> = array.iter().cloned().collect();
+    let prefixes: Vec<Option<String>> = values.iter().map(|v| raw(v.decor().prefix())).collect();
+    let mut suffixes: Vec<Option<String>> =
+        values.iter().map(|v| raw(v.decor().suffix())).collect();
+    let mut trailing = raw(Some(array.trailing())).unwrap_or_default();
+    let in_suffix = !array.trailing_comma();
+    // Splits the comment ending the previous line off the rest of the whitespace.
+    let split = |s: &str| match s.find('\n') {
+        Some(end) if s[..end].contains('#') => (s[..end].to_string(), s[end..].to_string()),
+        _ => (String::new(), s.to_string()),
+    };
+    let mut comments = Vec::with_capacity(values.len());
+    let mut rests = vec![prefixes.first().cloned().flatten()];
+    for prefix in prefixes.iter().skip(1) {
+        let (comment, rest) = prefix.as_deref().map(split).unzip();
+        comments.push(comment.unwrap_or_default());
+        rests.push(rest);
+    }
+    if let Some(last) = suffixes.last_mut().filter(|_| in_suffix) {
+        let (comment, rest) = last.as_deref().map(split).unzip();
+        comments.push(comment.unwrap_or_default());
+        *last = rest;
+    } else {
+        let (comment, rest) = split(&trailing);
+        comments.push(comment);
+        trailing = rest;
+    }
+    let join = |comment: &str, rest: Option<String>| match comment {
+        "" => rest,
+        comment => Some(format!("{comment}{}", rest.unwrap_or_default())),
+    };
+
+    array.clear();
+    for (j, &i) in order.iter().enumerate() {
+        let mut value = values[i].clone();
+        let prefix = match j {
+            0 => rests[0].clone(),
+            j => join(&comments[order[j - 1]], rests[j].clone()),
+        };
+        // The whitespace before the closing bracket stays with whichever element is last.
+        let last = j + 1 == order.len();
+        let suffix = suffixes[if last { values.len() - 1 } else { j }].clone();
+        let suffix = if last && in_suffix {
+            join(&comments[i], suffix)
+        } else {
+            suffix
+        };
+        let decor = value.decor_mut();
+        decor.clear();
+        if let Some(prefix) = prefix {
+            decor.set_prefix(prefix);
+        }
+        if let Some(suffix) = suffix {
+            decor.set_suffix(suffix);
+        }
+        array.push_formatted(value);
+    }
+    if let Some(&i) = order.last().filter(|_| !in_suffix) {
+        trailing = join(&comments[i], Some(trailing)).unwrap_or_default();
+    }
+    array.set_trailing(trailing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml_edit::DocumentMut;
+
+    fn doc(src: &str) -> DocumentMut {
+        src.parse().unwrap()
+    }
+
+    fn path(s: &str) -> KeyPath {
+        KeyPath::parse(s).unwrap()
+    }
+
+    fn pattern(s: &str) -> KeyPattern {
+        KeyPattern::parse(s).unwrap()
+    }
+
+    fn value(s: &str) -> Value {
+        s.parse().unwrap()
+    }
+
+    /// Runs `f` on the array `x` of `src`, returning the rendered document.
+    fn with_array(src: &str, f: impl FnOnce(&mut Array)) -> String {
+        let mut doc = doc(src);
+        f(doc["x"].as_array_mut().unwrap());
+        doc.to_string()
+    }
+
+    #[test]
+    fn sort_array_keeps_layout() {
+        assert_eq!(with_array("x = [3, 1, 2]\n", sort_array), "x = [1, 2, 3]\n");
+        assert_eq!(
+            with_array("x = [ 'b' ,  2, 'a', 1.5 ]\n", sort_array),
+            "x = [ 'a' ,  'b', 2, 1.5 ]\n"
+        );
+    }
+
+    #[test]
+    fn sort_array_moves_comments_with_elements() {
+        assert_eq!(
+            with_array("x = [\n  3, # three\n  1, # one\n]\n", sort_array),
+            "x = [\n  1, # one\n  3, # three\n]\n"
+        );
+        assert_eq!(
+            with_array("x = [\n  3, # three\n  1 # one\n]\n", sort_array),
+            "x = [\n  1, # one\n  3 # three\n]\n"
+        );
+        // Comments on lines of their own stay in place.
+        assert_eq!(
+            with_array(
+                "x = [ # list\n  # above\n  3,\n  1, # one\n  2,\n]\n",
+                sort_array
+            ),
+            "x = [ # list\n  # above\n  1, # one\n  2,\n  3,\n]\n"
+        );
+    }
+
+    #[test]
+    fn sort_array_by_field_moves_missing_fields_last() {
+        let sorted = with_array(
+            "x = [\n  {n = 2}, # two\n  3,\n  {n = 1}, # one\n  {m = 0},\n]\n",
+            |array| sort_array_by_field(array, "n"),
+        );
+        assert_eq!(
+            sorted,
+            "x = [\n  {n = 1}, # one\n  {n = 2}, # two\n  3,\n  {m = 0},\n]\n"
+        );
+    }
+
+    #[test]
+    fn dedupe_array_removes_later_duplicates_and_their_comments() {
+        let mut removed = 0;
+        let deduped = with_array("x = [\n  1, # a\n  0x1, # b\n  2,\n  1.0,\n]\n", |array| {
+            removed = dedupe_array(array)
+        });
+        assert_eq!(removed, 1);
+        assert_eq!(deduped, "x = [\n  1, # a\n  2,\n  1.0,\n]\n");
+    }
+
+    #[test]
+    fn fill_defaults_keeps_template_comments() {
+        let mut table = doc("[t]\nport = 80\n");
+        let template = doc("[t]\n# about host\nhost = \"h\" # after\nport = 1\n");
+        let added = fill_defaults(&mut table, &template);
+        assert_eq!(added, vec![path("t.host")]);
+        assert_eq!(
+            table.to_string(),
+            "[t]\nport = 80\n# about host\nhost = \"h\" # after\n"
+        );
+    }
+
+    #[test]
+    fn fill_defaults_fills_nested_and_inline_tables() {
+        let mut table = doc("a = 1\nt = {port = 80}\n");
+        let template = doc("t = { port = 1, host = \"h\" }\n# new\n[u]\nx = 1\n");
+        fill_defaults(&mut table, &template);
+        assert_eq!(
+            table.to_string(),
+            "a = 1\nt = { port = 80, host = \"h\" }\n# new\n[u]\nx = 1\n"
+        );
+        assert!(fill_defaults(&mut table, &template).is_empty());
+    }
+
+    #[test]
+    fn insert_at_places_new_keys() {
+        let cases = [
+            (Position::First, "bb = 9\nc = 1\nb = 2\n[t]\n"),
+            (Position::Last, "c = 1\nb = 2\nbb = 9\n[t]\n"),
+            (Position::After("c".into()), "c = 1\nbb = 9\nb = 2\n[t]\n"),
+            (Position::Sorted, "bb = 9\nc = 1\nb = 2\n[t]\n"),
+        ];
+        for (position, expected) in cases {
+            let mut table = doc("c = 1\nb = 2\n[t]\n");
+            let previous = insert_at(&mut table, "bb", toml_edit::value(9), position).unwrap();
+            assert!(previous.is_none());
+            assert_eq!(table.to_string(), expected);
+        }
+        let mut table = doc("c = 1\n");
+        let missing = insert_at(
+            &mut table,
+            "x",
+            toml_edit::value(1),
+            Position::After("z".into()),
+        );
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+    }
+
+    #[test]
+    fn insert_at_keeps_dotted_keys_in_order() {
+        let mut table = doc("c = 1\nb = 2 # b\na.b = 3\na.c = 4\n");
+        insert_at(
+            &mut table,
+            "bb",
+            toml_edit::value(9),
+            Position::After("c".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            table.to_string(),
+            "c = 1\nbb = 9\nb = 2 # b\na.b = 3\na.c = 4\n"
+        );
+    }
+
+    #[test]
+    fn insert_at_replaces_in_place() {
+        let mut table = doc("a = 1 # a\nb = 2\n");
+        let previous = insert_at(&mut table, "a", toml_edit::value(5), Position::Last).unwrap();
+        assert_eq!(previous.unwrap().as_integer(), Some(1));
+        assert_eq!(table.to_string(), "a = 5 # a\nb = 2\n");
+    }
+
+    #[test]
+    fn replace_table_keeps_the_kind_of_table() {
+        let contents = || Item::Table(doc("y = 2\nz.w = 3\n").as_table().clone());
+        let cases = [
+            (
+                "a.x = 1\nb = 2\n[t]\n",
+                "a",
+                "a.y = 2\na.z.w = 3\nb = 2\n[t]\n",
+            ),
+            (
+                "[s]\na.x = 1\nb = 2\n",
+                "s.a",
+                "[s]\na.y = 2\na.z.w = 3\nb = 2\n",
+            ),
+            (
+                "# c\n[a] # h\nx = 1\n[b]\n",
+                "a",
+                "# c\n[a] # h\ny = 2\nz.w = 3\n[b]\n",
+            ),
+            (
+                "a = {x = 1} # c\n",
+                "a",
+                "a = { y = 2, z = { w = 3 } } # c\n",
+            ),
+        ];
+        for (src, at, expected) in cases {
+            let mut table = doc(src);
+            let old = replace_table(&mut table, &path(at), contents()).unwrap();
+            assert_eq!(old.as_table_like().unwrap().len(), 1);
+            assert_eq!(table.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn replace_table_rejects_non_tables() {
+        let mut table = doc("a = 1\n");
+        let at = path("a");
+        let result = replace_table(&mut table, &at, Item::Table(Table::new()));
+        assert!(matches!(result, Err(Error::TypeMismatch { .. })));
+        let result = replace_table(&mut table, &at, toml_edit::value(1));
+        assert!(matches!(result, Err(Error::TypeMismatch { .. })));
+        let result = replace_table(&mut table, &path("b"), Item::None);
+        assert!(result.is_err());
+        assert_eq!(table.to_string(), "a = 1\n");
+    }
+
+    #[test]
+    fn remove_field_keeps_array_layout() {
+        let cases = [
+            ("x = [\n  1,\n  2\n]\n", "x[1]", "x = [\n  1\n]\n"),
+            ("x = [\n  1,\n  2,\n]\n", "x[1]", "x = [\n  1,\n]\n"),
+            (
+                "x = [\n  1, # one\n  2 # two\n]\n",
+                "x[1]",
+                "x = [\n  1 # one\n]\n",
+            ),
+            (
+                "x = [\n  1, # one\n  2 # two\n]\n",
+                "x[0]",
+                "x = [\n  2 # two\n]\n",
+            ),
+            ("x = [1, 2, 3]\n", "x[1]", "x = [1, 3]\n"),
+            ("x = [1]\n", "x[0]", "x = []\n"),
+        ];
+        for (src, at, expected) in cases {
+            let mut table = doc(src);
+            let removed = remove_field(&mut table, &path(at)).unwrap();
+            assert!(removed.is_some());
+            assert_eq!(table.to_string(), expected, "{src:?}");
+        }
+    }
+
+    #[test]
+    fn remove_field_removes_keys_and_tables() {
+        let mut table = doc("a = 1\nt = { b = 2, c = 3 }\n[[u]]\nx = 1\n[[u]]\nx = 2\n");
+        assert!(remove_field(&mut table, &path("t.b")).unwrap().is_some());
+        assert!(remove_field(&mut table, &path("u[0]")).unwrap().is_some());
+        assert!(remove_field(&mut table, &path("z")).unwrap().is_none());
+        assert_eq!(table.to_string(), "a = 1\nt = { c = 3 }\n[[u]]\nx = 2\n");
+        let missing = remove_field(&mut table, &path("u[1]"));
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+        let missing = remove_field(&mut table, &path("y.z"));
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+    }
+
+    #[test]
+    fn increment_keeps_format_and_comments() {
+        let mut table = doc("a = 0x10 # c\nb = [1, 0o7 ] # d\nc = 1_000\nd = 0b1\ne = 1.5\n");
+        let cases = [("a", 1, "0x11"), ("b[1]", 1, "0o10"), ("c", 999, "1_999")];
+        for (at, delta, expected) in cases {
+            let value = increment(&mut table, &path(at), delta).unwrap();
+            assert_eq!(value.to_string(), expected);
+        }
+        // Negative results can only be written in decimal.
+        increment(&mut table, &path("d"), -2).unwrap();
+        increment(&mut table, &path("e"), 1).unwrap();
+        assert_eq!(
+            table.to_string(),
+            "a = 0x11 # c\nb = [1, 0o10 ] # d\nc = 1_999\nd = -1\ne = 2.5\n"
+        );
+    }
+
+    #[test]
+    fn increment_rejects_overflow_and_non_numbers() {
+        let mut table = doc("a = 9223372036854775807\nb = \"x\"\n");
+        let overflow = increment(&mut table, &path("a"), 1);
+        assert!(matches!(overflow, Err(Error::Unrepresentable { .. })));
+        let mismatch = increment(&mut table, &path("b"), 1);
+        assert!(matches!(mismatch, Err(Error::TypeMismatch { .. })));
+        let missing = increment(&mut table, &path("c"), 1);
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+        assert_eq!(table.to_string(), "a = 9223372036854775807\nb = \"x\"\n");
+    }
+
+    #[test]
+    fn append_unique_appends_like_the_last_element() {
+        let mut appended = vec![];
+        let rendered = with_array("x = [\n  1, # one\n  2\n]\n", |array| {
+            appended.push(append_unique(array, value("0x2")));
+            appended.push(append_unique(array, value("3")));
+            assert!(array_contains(array, &value("3")));
+            assert!(!array_contains(array, &value("3.0")));
+        });
+        assert_eq!(appended, [false, true]);
+        assert_eq!(rendered, "x = [\n  1, # one\n  2,\n  3\n]\n");
+        let rendered = with_array("x = []\n", |array| {
+            append_unique(array, value("'a'"));
+        });
+        assert_eq!(rendered, "x = ['a']\n");
+    }
+
+    #[test]
+    fn set_field_writes_single_fields() {
+        let mut table = doc("a = 1 # a\nt = { b = 2, c = [1, 2] }\n[[u]]\nx = 1\n");
+        let previous = set_field(&mut table, &path("a"), value("5")).unwrap();
+        assert_eq!(previous.unwrap().as_integer(), Some(1));
+        set_field(&mut table, &path("t.b"), value("'x'")).unwrap();
+        set_field(&mut table, &path("t.c[1]"), value("3")).unwrap();
+        assert!(set_field(&mut table, &path("u[0].y"), value("2"))
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            table.to_string(),
+            "a = 5 # a\nt = { b = 'x', c = [1, 3] }\n[[u]]\nx = 1\ny = 2\n"
+        );
+        let missing = set_field(&mut table, &path("t.c[2]"), value("1"));
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+        let missing = set_field(&mut table, &path("v.w"), value("1"));
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+        let mismatch = set_field(&mut table, &path("a.b"), value("1"));
+        assert!(matches!(mismatch, Err(Error::TypeMismatch { .. })));
+        let empty = set_field(&mut table, &KeyPath::new(), value("1"));
+        assert!(matches!(empty, Err(Error::EmptyPath)));
+    }
+
+    #[test]
+    fn set_default_writes_only_missing_values() {
+        let mut table = doc("[server]\nport = 80\n");
+        assert!(!set_default(&mut table, &path("server.port"), value("1")).unwrap());
+        assert!(set_default(&mut table, &path("server.host"), value("'h'")).unwrap());
+        assert!(set_default(&mut table, &path("x.y"), value("1")).is_err());
+        assert_eq!(table.to_string(), "[server]\nport = 80\nhost = 'h'\n");
+    }
+
+    #[test]
+    fn insert_at_index_lays_out_like_neighbours() {
+        let cases = [
+            ("x = [1, 2]\n", "x[0]", "x = [0, 1, 2]\n"),
+            ("x = [1, 2]\n", "x[1]", "x = [1, 0, 2]\n"),
+            ("x = [1, 2]\n", "x[2]", "x = [1, 2, 0]\n"),
+            ("x = []\n", "x[0]", "x = [0]\n"),
+            (
+                "x = [\n  # first\n  1,\n  2\n]\n",
+                "x[0]",
+                "x = [\n  # first\n  0,\n  1,\n  2\n]\n",
+            ),
+            (
+                "x = [\n  1,\n  2,\n]\n",
+                "x[2]",
+                "x = [\n  1,\n  2,\n  0,\n]\n",
+            ),
+        ];
+        for (src, at, expected) in cases {
+            let mut table = doc(src);
+            insert_at_index(&mut table, &path(at), value("0")).unwrap();
+            assert_eq!(table.to_string(), expected, "{src:?} {at}");
+        }
+        let mut table = doc("x = [1]\ny = 1\n");
+        let past_end = insert_at_index(&mut table, &path("x[2]"), value("0"));
+        assert!(matches!(past_end, Err(Error::MissingKey { .. })));
+        let mismatch = insert_at_index(&mut table, &path("y[0]"), value("0"));
+        assert!(matches!(mismatch, Err(Error::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn find_and_remove_matching() {
+        let src = "[a]\nx = [1, 2]\n[b]\nx = 3\ny = 4\n[[c]]\nx = 5\n[[c]]\nx = 6\n";
+        let table = doc(src);
+        let found = |p: &str| -> Vec<String> {
+            let paths = find_matching(&table, &pattern(p));
+            paths.iter().map(ToString::to_string).collect()
+        };
+        assert_eq!(found("*.x"), ["a.x", "b.x"]);
+        assert_eq!(found("c[*].x"), ["c[0].x", "c[1].x"]);
+        assert_eq!(found("a.x[*]"), ["a.x[0]", "a.x[1]"]);
+        assert!(found("z.*").is_empty());
+
+        let mut table = doc(src);
+        let removed = remove_matching(&mut table, &pattern("a.x[*]"));
+        assert_eq!(removed, [path("a.x[0]"), path("a.x[1]")]);
+        let removed = remove_matching(&mut table, &pattern("c[*].x"));
+        assert_eq!(removed.len(), 2);
+        assert_eq!(
+            table.to_string(),
+            "[a]\nx = []\n[b]\nx = 3\ny = 4\n[[c]]\n[[c]]\n"
+        );
+    }
+
+    #[test]
+    fn clear_table_keeps_or_drops_the_header() {
+        let src = "a = 1\n# about t\n[t] # t\nx = 1\n[t.u]\ny = 2\n[v]\ni = { z = 1 }\n";
+        let mut table = doc(src);
+        let before = clear_table(&mut table, &path("t"), true).unwrap();
+        assert_eq!(before.as_table().unwrap().len(), 2);
+        assert_eq!(
+            table.to_string(),
+            "a = 1\n# about t\n[t] # t\n[v]\ni = { z = 1 }\n"
+        );
+        let mut table = doc(src);
+        clear_table(&mut table, &path("t"), false).unwrap();
+        clear_table(&mut table, &path("v.i"), false).unwrap();
+        assert_eq!(table.to_string(), "a = 1\n[v]\ni = {}\n");
+        clear_table(&mut table, &KeyPath::new(), false).unwrap();
+        assert_eq!(table.to_string(), "");
+        let mut table = doc(src);
+        let mismatch = clear_table(&mut table, &path("a"), true);
+        assert!(matches!(mismatch, Err(Error::TypeMismatch { .. })));
+        let missing = clear_table(&mut table, &path("z"), true);
+        assert!(matches!(missing, Err(Error::MissingKey { .. })));
+    }
+
+    #[test]
+    fn prune_empty_removes_nested_empty_tables() {
+        let mut table =
+            doc("a = 1\ne = {}\n[t]\n[t.u]\nv = {}\n[w]\nx = 1\n[[arr]]\n[[arr]]\nk = {}\n");
+        let removed = prune_empty(&mut table);
+        assert_eq!(removed, [path("e"), path("t"), path("arr[1].k")]);
+        assert_eq!(table.to_string(), "a = 1\n[w]\nx = 1\n[[arr]]\n[[arr]]\n");
+        assert!(prune_empty(&mut table).is_empty());
+    }
+
+    #[test]
+    fn prune_parents_stops_at_the_first_non_empty_table() {
+        let mut table = doc("[a]\nk = 1\n[a.b]\n[a.b.c]\nd = {}\n");
+        assert_eq!(
+            prune_parents(&mut table, &path("a.b.c.d.gone")),
+            Some(path("a.b"))
+        );
+        assert_eq!(table.to_string(), "[a]\nk = 1\n");
+        assert_eq!(prune_parents(&mut table, &path("a.k")), None);
+        let mut table = doc("[[a]]\n");
+        assert_eq!(prune_parents(&mut table, &path("a[0].x")), None);
+        assert_eq!(table.to_string(), "[[a]]\n");
+    }
+
+    #[test]
+    fn drift_reports_changed_missing_and_extra_keys() {
+        let table =
+            doc("a = 1\nb = 0x2\nc = [1]\n[t]\nx = 'x'\nextra = 1\n[__constraints]\n\"a\" = {}\n");
+        let template =
+            doc("a = 1.0\nb = 2\nc = [1, 2]\nmissing = 1\nt = { x = \"x\", y = 1 }\n[u]\n");
+        let drift = drift(&table, &template);
+        assert_eq!(drift.changed, [path("a"), path("c")]);
+        assert_eq!(drift.missing, [path("missing"), path("t.y"), path("u")]);
+        assert_eq!(drift.extra, [path("t.extra")]);
+        assert!(!drift.is_empty());
+        assert!(super::drift(&template, &template).is_empty());
+    }
+
+    #[test]
+    fn sort_table_sorts_plain_keys_only() {
+        let mut table = doc("# c\nc = 3\nb = 2 # b\na.x = 1\n[z]\n[y]\n");
+        sort_table(&mut table);
+        assert_eq!(
+            table.to_string(),
+            "a.x = 1\nb = 2 # b\n# c\nc = 3\n[z]\n[y]\n"
+        );
+    }
+
+    #[test]
+    fn string_value_writes_each_kind() {
+        let cases = [
+            ("a\"b\\c\n", StringKind::Basic, r#""a\"b\\c\n""#),
+            ("a\"b\n", StringKind::MultilineBasic, "\"\"\"\na\"b\n\"\"\""),
+            ("a\"b\\c", StringKind::Literal, r#"'a"b\c'"#),
+            ("it's\n", StringKind::MultilineLiteral, "'''\nit's\n'''"),
+        ];
+        for (s, kind, expected) in cases {
+            let value = string_value(s, kind).unwrap();
+            assert_eq!(value.to_string(), expected);
+            assert_eq!(value.as_str(), Some(s));
+        }
+        let quotes = string_value("\"\"\"\"", StringKind::MultilineBasic).unwrap();
+        assert_eq!(quotes.as_str(), Some("\"\"\"\""));
+        for (s, kind) in [
+            ("it's", StringKind::Literal),
+            ("a\nb", StringKind::Literal),
+            ("'''", StringKind::MultilineLiteral),
+            ("a\rb", StringKind::MultilineLiteral),
+        ] {
+            let err = string_value(s, kind).unwrap_err();
+            assert!(matches!(err, Error::Unrepresentable { .. }), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn integer_value_and_parse_integer_keep_syntax() {
+        let cases = [
+            (IntFormat::Decimal, "1000000"),
+            (IntFormat::Grouped, "1_000_000"),
+            (IntFormat::Hex, "0xf4240"),
+            (IntFormat::Octal, "0o3641100"),
+            (IntFormat::Binary, "0b11110100001001000000"),
+        ];
+        for (format, expected) in cases {
+            let value = integer_value(1_000_000, format).unwrap();
+            assert_eq!(value.to_string(), expected);
+            assert_eq!(parse_integer(expected).unwrap().to_string(), expected);
+        }
+        assert_eq!(
+            integer_value(-1234, IntFormat::Grouped)
+                .unwrap()
+                .to_string(),
+            "-1_234"
+        );
+        let negative = integer_value(-1, IntFormat::Hex);
+        assert!(matches!(negative, Err(Error::Unrepresentable { .. })));
+        assert_eq!(parse_integer(" 0x1F ").unwrap().as_integer(), Some(31));
+        assert!(matches!(
+            parse_integer("1.5"),
+            Err(Error::TypeMismatch { .. })
+        ));
+        assert!(matches!(parse_integer("0x"), Err(Error::Toml(_))));
+    }
+
+    #[test]
+    fn float_value_reads_back_exactly() {
+        let cases = [
+            (1.5e6, FloatFormat::Plain, "1500000.0"),
+            (1.5e6, FloatFormat::Exponent, "1.5e6"),
+            (0.1, FloatFormat::Plain, "0.1"),
+            (f64::INFINITY, FloatFormat::Plain, "inf"),
+            (f64::NEG_INFINITY, FloatFormat::Exponent, "-inf"),
+        ];
+        for (f, format, expected) in cases {
+            let value = float_value(f, format);
+            assert_eq!(value.to_string(), expected);
+            assert_eq!(value.as_float(), Some(f));
+        }
+        assert!(float_value(f64::NAN, FloatFormat::Default)
+            .as_float()
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn non_finite_floats_are_found_and_rejected() {
+        let table = doc("a = 1.0\nb = nan\n[t]\nc = [1.0, -inf]\nd = { e = +inf }\n");
+        assert_eq!(
+            non_finite_floats(&table),
+            [path("b"), path("t.c[1]"), path("t.d.e")]
+        );
+        assert!(reject_non_finite(&path("x"), &value("[1.0, { y = 2.0 }]")).is_ok());
+        match reject_non_finite(&path("x"), &value("[1.0, { y = nan }]")) {
+            Err(Error::Unrepresentable { path: at, .. }) => assert_eq!(at, path("x[1].y")),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn compare_orders_values_ignoring_format() {
+        assert_eq!(compare(&value("0x10"), &value("16")), Ordering::Equal);
+        assert_eq!(compare(&value("'b'"), &value("1")), Ordering::Less);
+        assert_eq!(compare(&value("nan"), &value("inf")), Ordering::Greater);
+        assert_eq!(compare(&value("-nan"), &value("+nan")), Ordering::Equal);
+        assert_eq!(compare(&value("[1, 2]"), &value("[1]")), Ordering::Greater);
+        assert!(value_eq(&value("3.0"), &value("3.00")));
+        assert!(value_eq(&value("{ a = 1, b = 2 }"), &value("{b=2,a=1}")));
+        assert!(!value_eq(&value("1"), &value("1.0")));
+    }
+
+    #[test]
+    fn toml_eq_ignores_table_syntax() {
+        let standard = doc("[t]\na = 1\n[[u]]\nb = 2\n");
+        let inline = doc("t = { a = 0x1 }\nu = [{ b = 2 }]\n");
+        assert!(toml_eq(
+            &Item::Table(standard.as_table().clone()),
+            &Item::Table(inline.as_table().clone())
+        ));
+        let other = doc("t = { a = 1 }\nu = [{ b = 3 }]\n");
+        assert!(!toml_eq(
+            &Item::Table(standard.as_table().clone()),
+            &Item::Table(other.as_table().clone())
+        ));
+    }
+}
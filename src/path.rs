@@ -0,0 +1,510 @@
+//! Addressing values inside a document.
+//!
+//! A [`KeyPath`] is written the way keys are written in TOML, with array indices appended in
+//! brackets: `server.endpoints[0]."host.name"`. Keys that are not bare keys are quoted, either
+//! as basic strings (`"a.b"`) or literal strings (`'a.b'`), so dots inside a key are never
+//! mistaken for separators.
+
+use crate::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// One step of a [`KeyPath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// A key of a table or inline table.
+    Key(String),
+    /// An index into an array or array of tables.
+    Index(usize),
+}
+
+/// The location of a value in a document, as a list of keys and array indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyPath {
+    segments: Vec<Segment>,
+}
+
+impl KeyPath {
+    /// Creates an empty path, which addresses the root table.
+    pub fn new() -> Self {
+        KeyPath::default()
+    }
+
+    /// Parses a path such as `a."b.c".d[2]`.
+    ///
+    /// The empty string parses to the empty path. A path may start with an index, such as
+    /// `[0].name`, for paths relative to an array.
+    pub fn parse(input: &str) -> Result<KeyPath> {
+        let segments = Parser::new(input, false).parse()?;
+        Ok(segments
+            .into_iter()
+            .map(|segment| match segment {
+                PatternSegment::Exact(segment) => segment,
+                _ => unreachable!("wildcards are rejected when parsing paths"),
+            })
+            .collect())
+    }
+
+    /// Returns the segments of the path.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns true if the path addresses the root table.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the number of segments in the path.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the last segment of the path.
+    pub fn last(&self) -> Option<&Segment> {
+        self.segments.last()
+    }
+
+    /// Returns the path without its last segment, or `None` for the empty path.
+    pub fn parent(&self) -> Option<KeyPath> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(KeyPath {
+            segments: parent.to_vec(),
+        })
+    }
+
+    /// Appends a segment.
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// Appends a key.
+    pub fn push_key(&mut self, key: impl Into<String>) {
+        self.push(Segment::Key(key.into()));
+    }
+
+    /// Appends an array index.
+    pub fn push_index(&mut self, index: usize) {
+        self.push(Segment::Index(index));
+    }
+
+    /// Removes and returns the last segment.
+    pub fn pop(&mut self) -> Option<Segment> {
+        self.segments.pop()
+    }
+
+    /// Returns a copy of this path with `key` appended.
+    pub fn with_key(&self, key: impl Into<String>) -> KeyPath {
+        let mut path = self.clone();
+        path.push_key(key);
+        path
+    }
+
+    /// Returns a copy of this path with an array index appended.
+    pub fn with_index(&self, index: usize) -> KeyPath {
+        let mut path = self.clone();
+        path.push_index(index);
+        path
+    }
+}
+
+impl FromStr for KeyPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        KeyPath::parse(s)
+    }
+}
+
+impl FromIterator<Segment> for KeyPath {
+    fn from_iter<T: IntoIterator<Item = Segment>>(iter: T) -> Self {
+        KeyPath {
+            segments: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for KeyPath {
+    /// Formats the path so that [`KeyPath::parse`] returns it unchanged.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    write_key(f, key)?;
+                }
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One step of a [`KeyPattern`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PatternSegment {
+    /// Matches exactly this key or index.
+    Exact(Segment),
+    /// `*`, matching any key.
+    AnyKey,
+    /// `[*]`, matching any array index.
+    AnyIndex,
+}
+
+/// A [`KeyPath`] with wildcards, such as `cache.*.ttl` or `servers[*].port`.
+///
+/// Each wildcard stands for exactly one segment, so a pattern only matches paths of its own
+/// length.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyPattern {
+    segments: Vec<PatternSegment>,
+}
+
+impl KeyPattern {
+    /// Parses a pattern, written like a [`KeyPath`] with `*` in place of any key and `[*]` in
+    /// place of any index. A quoted `"*"` is a literal key.
+    pub fn parse(input: &str) -> Result<KeyPattern> {
+        Ok(KeyPattern {
+            segments: Parser::new(input, true).parse()?,
+        })
+    }
+
+    /// Returns the segments of the pattern.
+    pub fn segments(&self) -> &[PatternSegment] {
+        &self.segments
+    }
+
+    /// Returns whether `path` matches the pattern.
+    pub fn matches(&self, path: &KeyPath) -> bool {
+        self.segments.len() == path.len()
+            && self
+                .segments
+                .iter()
+                .zip(path.segments())
+                .all(|(pattern, segment)| match (pattern, segment) {
+                    (PatternSegment::Exact(exact), segment) => exact == segment,
+                    (PatternSegment::AnyKey, Segment::Key(_)) => true,
+                    (PatternSegment::AnyIndex, Segment::Index(_)) => true,
+                    _ => false,
+                })
+    }
+}
+
+impl FromStr for KeyPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        KeyPattern::parse(s)
+    }
+}
+
+impl fmt::Display for KeyPattern {
+    /// Formats the pattern so that [`KeyPattern::parse`] returns it unchanged.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0
+                && !matches!(
+                    segment,
+                    PatternSegment::AnyIndex | PatternSegment::Exact(Segment::Index(_))
+                )
+            {
+                f.write_str(".")?;
+            }
+            match segment {
+                PatternSegment::Exact(Segment::Key(key)) => write_key(f, key)?,
+                PatternSegment::Exact(Segment::Index(index)) => write!(f, "[{index}]")?,
+                PatternSegment::AnyKey => f.write_str("*")?,
+                PatternSegment::AnyIndex => f.write_str("[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_bare(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn write_key(f: &mut fmt::Formatter<'_>, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_bare) {
+        return f.write_str(key);
+    }
+    f.write_str("\"")?;
+    for c in key.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if c.is_control() => write!(f, "\\u{:04X}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    /// Whether `*` and `[*]` are accepted.
+    wildcards: bool,
+    segments: Vec<PatternSegment>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, wildcards: bool) -> Self {
+        Parser {
+            input,
+            pos: 0,
+            wildcards,
+            segments: vec![],
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<PatternSegment>> {
+        self.skip_ws();
+        if self.peek().is_none() {
+            return Ok(self.segments);
+        }
+        // Paths relative to an array start with an index.
+        let first = if self.peek() == Some('[') {
+            self.pos += 1;
+            self.index()?
+        } else {
+            self.key()?
+        };
+        self.segments.push(first);
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => return Ok(self.segments),
+                Some('.') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    let key = self.key()?;
+                    self.segments.push(key);
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let index = self.index()?;
+                    self.segments.push(index);
+                }
+                Some(_) => return Err(self.error("expected `.`, `[` or end of path")),
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, reason: &'static str) -> Error {
+        Error::InvalidPath {
+            input: self.input.to_string(),
+            offset: self.pos,
+            reason,
+        }
+    }
+
+    fn key(&mut self) -> Result<PatternSegment> {
+        let key = match self.peek() {
+            Some('"') => self.basic_string()?,
+            Some('\'') => self.literal_string()?,
+            Some('*') if self.wildcards => {
+                self.pos += 1;
+                return Ok(PatternSegment::AnyKey);
+            }
+            Some(c) if is_bare(c) => {
+                let start = self.pos;
+                while self.peek().is_some_and(is_bare) {
+                    self.pos += 1;
+                }
+                self.input[start..self.pos].to_string()
+            }
+            _ => return Err(self.error("expected a key")),
+        };
+        Ok(PatternSegment::Exact(Segment::Key(key)))
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(self.input[start..self.pos - 1].to_string()),
+                Some(_) => {}
+                None => return Err(self.error("unterminated literal string")),
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut key = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(key),
+                Some('\\') => key.push(self.escape()?),
+                Some(c) => key.push(c),
+                None => return Err(self.error("unterminated basic string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        let c = match self.bump() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('e') => '\u{1b}',
+            Some('u') => return self.unicode(4),
+            Some('U') => return self.unicode(8),
+            _ => return Err(self.error("invalid escape sequence")),
+        };
+        Ok(c)
+    }
+
+    fn unicode(&mut self, len: usize) -> Result<char> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + len)
+            .filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let c = u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid unicode scalar value"))?;
+        self.pos += len;
+        Ok(c)
+    }
+
+    fn index(&mut self) -> Result<PatternSegment> {
+        self.skip_ws();
+        if self.wildcards && self.peek() == Some('*') {
+            self.pos += 1;
+            self.close_index()?;
+            return Ok(PatternSegment::AnyIndex);
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let index = self.input[start..self.pos]
+            .parse()
+            .map_err(|_| self.error("expected an array index"))?;
+        self.close_index()?;
+        Ok(PatternSegment::Exact(Segment::Index(index)))
+    }
+
+    fn close_index(&mut self) -> Result<()> {
+        self.skip_ws();
+        if self.bump() != Some(']') {
+            return Err(self.error("expected `]`"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[Segment]) -> KeyPath {
+        segments.iter().cloned().collect()
+    }
+
+    fn key(k: &str) -> Segment {
+        Segment::Key(k.to_string())
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let paths = [
+            KeyPath::new(),
+            path(&[key("a")]),
+            path(&[key("a"), key("b-c_1"), Segment::Index(3)]),
+            path(&[key("a.b"), key("")]),
+            path(&[key(""), key("")]),
+            path(&[key("with space"), key("quote\"d"), key("back\\slash")]),
+            path(&[
+                key("tab\there"),
+                key("line\nbreak"),
+                key("cr\r"),
+                key("bell\u{7}"),
+            ]),
+            path(&[key("é"), key("*"), key("'")]),
+            path(&[Segment::Index(0), key("name")]),
+            path(&[Segment::Index(1), Segment::Index(2)]),
+        ];
+        for p in paths {
+            let text = p.to_string();
+            assert_eq!(KeyPath::parse(&text).unwrap(), p, "{text}");
+        }
+    }
+
+    #[test]
+    fn parses_toml_key_syntax() {
+        assert_eq!(
+            KeyPath::parse(r#"a."b.c".d[2]"#).unwrap(),
+            path(&[key("a"), key("b.c"), key("d"), Segment::Index(2)])
+        );
+        assert_eq!(
+            KeyPath::parse(r#" a . 'lit\n' [ 1 ] . "é\t" "#).unwrap(),
+            path(&[key("a"), key("lit\\n"), Segment::Index(1), key("é\t")])
+        );
+        assert_eq!(KeyPath::parse(r#""""#).unwrap(), path(&[key("")]));
+        assert_eq!(KeyPath::parse("").unwrap(), KeyPath::new());
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        for input in [
+            "a.", ".a", "a[", "a[x]", "a[1", "a b", r#""open"#, "'open", r#""\q""#, "*",
+        ] {
+            assert!(KeyPath::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn patterns_match_one_segment_per_wildcard() {
+        let pattern = KeyPattern::parse("cache.*.ttl").unwrap();
+        assert!(pattern.matches(&KeyPath::parse("cache.a.ttl").unwrap()));
+        assert!(!pattern.matches(&KeyPath::parse("cache.a.b.ttl").unwrap()));
+        assert!(!pattern.matches(&KeyPath::parse("cache[0].ttl").unwrap()));
+
+        let pattern = KeyPattern::parse("servers[*].port").unwrap();
+        assert!(pattern.matches(&KeyPath::parse("servers[3].port").unwrap()));
+        assert!(!pattern.matches(&KeyPath::parse("servers.a.port").unwrap()));
+
+        let literal = KeyPattern::parse(r#""*""#).unwrap();
+        assert!(literal.matches(&path(&[key("*")])));
+        assert!(!literal.matches(&path(&[key("a")])));
+    }
+
+    #[test]
+    fn pattern_display_round_trips() {
+        for input in ["*", "[*]", "a.*[*].b", r#""*".x[2]"#, "[0].*"] {
+            let pattern = KeyPattern::parse(input).unwrap();
+            assert_eq!(KeyPattern::parse(&pattern.to_string()).unwrap(), pattern);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Reading single tables from large documents without parsing the whole document.
+//!
+//! [`read_table`] runs a lightweight lexer over the source that only recognises strings,
+//! comments, brackets, table headers and the first key of each key/value line. That is enough
+//! to cut out the sections belonging to one table and parse just those. When the table might
+//! also be defined somewhere the scanner cannot see cheaply (dotted keys or an inline table in a
+//! parent section, or a parent array of tables), it falls back to parsing the whole document.
+//!
+//! The fast path does not validate the rest of the document.
+
+use crate::path::{KeyPath, Segment};
+use crate::walk::{get, Node};
+use crate::{Error, Result};
+use toml_edit::{DocumentMut, Table, Value};
+
+/// Returns a copy of the table at `path`, or `None` if the document has no such table.
+///
+/// Fails with [`Error::TypeMismatch`] if `path` holds something other than a table.
+pub fn read_table(src: &str, path: &KeyPath) -> Result<Option<Table>> {
+    if let Some(sections) = plan(src, path) {
+        let mut part = String::new();
+        for section in sections {
+            part.push_str(&src[section.start..section.end]);
+        }
+        let doc: DocumentMut = part.parse().map_err(Error::Toml)?;
+        return to_table(get(&doc, path), path);
+    }
+    let doc: DocumentMut = src.parse().map_err(Error::Toml)?;
+    to_table(get(&doc, path), path)
+}
+
+fn to_table(node: Option<Node<'_>>, path: &KeyPath) -> Result<Option<Table>> {
+    match node {
+        None => Ok(None),
+        Some(Node::Table(table)) => Ok(Some(table.clone())),
+        Some(Node::Value(Value::InlineTable(table))) => Ok(Some(table.clone().into_table())),
+        Some(node) => Err(Error::TypeMismatch {
+            path: path.clone(),
+            expected: "a table",
+            found: node.type_name(),
+        }),
+    }
+}
+
+/// Internal helpers exposed for benchmarking.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench {
+    /// Runs the section lexer, returning the number of sections found.
+    pub fn sections(src: &str) -> Option<usize> {
+        super::sections(src).map(|sections| sections.len())
+    }
+}
+
+/// Picks the sections that make up the table at `path`, or returns `None` if the whole
+/// document has to be parsed.
+fn plan(src: &str, path: &KeyPath) -> Option<Vec<Section>> {
+    if path.is_empty()
+        || path
+            .segments()
+            .iter()
+            .any(|s| matches!(s, Segment::Index(_)))
+    {
+        return None;
+    }
+    let sections = sections(src)?;
+    let mut wanted = vec![];
+    for section in sections {
+        let header = section.path.segments();
+        if header.starts_with(path.segments()) {
+            if header.len() == path.len() && section.array {
+                return None;
+            }
+            wanted.push(section);
+        } else if path.segments().starts_with(header) {
+            let Segment::Key(next) = &path.segments()[header.len()] else {
+                return None;
+            };
+            let defines_next = section.keys.iter().any(
+                |key| matches!(key.path.segments().first(), Some(Segment::Key(k)) if k == next),
+            );
+            if section.array || defines_next {
+                return None;
+            }
+        }
+    }
+    Some(wanted)
+}
+
+/// A table header and everything up to the next one.
+#[derive(Debug)]
+pub(crate) struct Section {
+    pub(crate) path: KeyPath,
+    pub(crate) array: bool,
+    pub(crate) start: usize,
+    /// Offset just past the header, or `start` for the root section.
+    pub(crate) body: usize,
+    pub(crate) end: usize,
+    /// Key of every key/value line in the section.
+    pub(crate) keys: Vec<SectionKey>,
+}
+
+/// The key of a key/value line.
+#[derive(Debug)]
+pub(crate) struct SectionKey {
+    pub(crate) path: KeyPath,
+    /// Offset of the start of the key.
+    pub(crate) start: usize,
+    /// Whether the value is an inline table.
+    pub(crate) inline_table: bool,
+}
+
+/// Splits `src` into sections, starting with the headerless root section.
+///
+/// Returns `None` if the source is not lexically valid, leaving the error to the full parser.
+pub(crate) fn sections(src: &str) -> Option<Vec<Section>> {
+    let bytes = src.as_bytes();
+    let mut sections = vec![Section {
+        path: KeyPath::new(),
+        array: false,
+        start: 0,
+        body: 0,
+        end: src.len(),
+        keys: vec![],
+    }];
+    let mut depth = 0usize;
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        if line_start && depth == 0 {
+            line_start = false;
+            let begin = i;
+            while matches!(bytes.get(i), Some(b' ' | b'\t')) {
+                i += 1;
+            }
+            match bytes.get(i) {
+                Some(b'[') => {
+                    let (path, array, end) = header(src, i)?;
+                    if let Some(last) = sections.last_mut() {
+                        last.end = begin;
+                    }
+                    sections.push(Section {
+                        path,
+                        array,
+                        start: begin,
+                        body: end,
+                        end: src.len(),
+                        keys: vec![],
+                    });
+                    i = end;
+                    continue;
+                }
+                Some(b'#' | b'\r' | b'\n') | None => {}
+                Some(_) => {
+                    let eq = key_end(src, i)?;
+                    let path = KeyPath::parse(src[i..eq].trim()).ok()?;
+                    let inline_table = src[eq + 1..]
+                        .trim_start_matches([' ', '\t'])
+                        .starts_with('{');
+                    sections.last_mut()?.keys.push(SectionKey {
+                        path,
+                        start: i,
+                        inline_table,
+                    });
+                    i = eq + 1;
+                    continue;
+                }
+            }
+        }
+        match bytes[i] {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'"' | b'\'' => {
+                i = string_end(bytes, i)?;
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.checked_sub(1)?,
+            b'\n' => line_start = true,
+            _ => {}
+        }
+        i += 1;
+    }
+    Some(sections)
+}
+
+/// Parses the table header starting at `i`, returning its path, whether it is an array of
+/// tables header, and the offset just past it.
+fn header(src: &str, i: usize) -> Option<(KeyPath, bool, usize)> {
+    let bytes = src.as_bytes();
+    let array = bytes.get(i + 1) == Some(&b'[');
+    let start = if array { i + 2 } else { i + 1 };
+    let mut end = start;
+    loop {
+        match bytes.get(end)? {
+            b'"' | b'\'' => end = string_end(bytes, end)?,
+            b']' => break,
+            b'\n' => return None,
+            _ => end += 1,
+        }
+    }
+    let path = KeyPath::parse(src[start..end].trim()).ok()?;
+    let close = if array { "]]" } else { "]" };
+    if !src[end..].starts_with(close) {
+        return None;
+    }
+    Some((path, array, end + close.len()))
+}
+
+/// Returns the offset of the `=` ending the key that starts at `i`.
+fn key_end(src: &str, mut i: usize) -> Option<usize> {
+    let bytes = src.as_bytes();
+    loop {
+        match bytes.get(i)? {
+            b'"' | b'\'' => i = string_end(bytes, i)?,
+            b'=' => return Some(i),
+            b'\n' => return None,
+            _ => i += 1,
+        }
+    }
+}
+
+/// Returns the offset just past the string starting at `i`.
+fn string_end(bytes: &[u8], i: usize) -> Option<usize> {
+    let quote = bytes[i];
+    let multiline = bytes.get(i + 1) == Some(&quote) && bytes.get(i + 2) == Some(&quote);
+    let mut j = if multiline { i + 3 } else { i + 1 };
+    loop {
+        let c = *bytes.get(j)?;
+        if c == b'\\' && quote == b'"' {
+            j += 2;
+        } else if c == quote && !multiline {
+            return Some(j + 1);
+        } else if c == quote && bytes.get(j + 1) == Some(&quote) && bytes.get(j + 2) == Some(&quote)
+        {
+            // Up to two quotes may directly precede the closing delimiter.
+            let mut end = j + 3;
+            while end < j + 5 && bytes.get(end) == Some(&quote) {
+                end += 1;
+            }
+            return Some(end);
+        } else if c == b'\n' && !multiline {
+            return None;
+        } else {
+            j += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::toml_eq;
+    use toml_edit::Item;
+
+    /// Reads the table at `path` and checks it against a full parse, returning whether the fast
+    /// path was taken.
+    fn check(src: &str, path: &str) -> bool {
+        let path = KeyPath::parse(path).unwrap();
+        let doc: DocumentMut = src.parse().unwrap();
+        let expected = to_table(get(&doc, &path), &path).unwrap();
+        let actual = read_table(src, &path).unwrap();
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => assert!(
+                toml_eq(&Item::Table(expected.clone()), &Item::Table(actual.clone())),
+                "{path}: expected\n{expected}\nfound\n{actual}"
+            ),
+            (expected, actual) => assert_eq!(expected.is_some(), actual.is_some(), "{path}"),
+        }
+        plan(src, &path).is_some()
+    }
+
+    #[test]
+    fn reads_headed_tables_from_their_sections() {
+        let src = "top = 1\n[a]\nx = 1\n[b]\ny = 2\n[a.c]\nz = 3\n[[a.list]]\nn = 1\n";
+        assert!(check(src, "a"));
+        assert!(check(src, "a.c"));
+        assert!(check(src, "b"));
+        assert!(check(src, "missing"));
+    }
+
+    #[test]
+    fn falls_back_for_dotted_keys_in_a_parent() {
+        let src = "a.x = 1\n[b]\ny = 2\n";
+        assert!(!check(src, "a"));
+        let src = "[t]\nsub.k = 1\n[t.other]\nk = 2\n";
+        assert!(!check(src, "t.sub"));
+        assert!(check(src, "t.other"));
+    }
+
+    #[test]
+    fn falls_back_for_an_inline_table_in_a_parent() {
+        let src = "a = { x = 1, c = { y = 2 } }\n[b]\ny = 2\n";
+        assert!(!check(src, "a"));
+        assert!(!check(src, "a.c"));
+    }
+
+    #[test]
+    fn falls_back_below_a_parent_array_of_tables() {
+        let src = "[[p]]\n[p.q]\nx = 1\n[[p]]\n[p.q]\nx = 2\n";
+        assert!(!check(src, "p.q"));
+        assert!(!check(src, "p[1].q"));
+    }
+
+    #[test]
+    fn ignores_headers_inside_strings_and_comments() {
+        let src = concat!(
+            "s = \"\"\"\n[a]\nfake = 1\n\"\"\"\n",
+            "l = '''\n[a]\nfake = 2\n'''\n",
+            "q = \"[a]\" # [a]\n",
+            "# [a]\n",
+            "v = [\n  \"[a]\",\n  1,\n]\n",
+            "[a]\nreal = 1\n",
+        );
+        assert!(check(src, "a"));
+        let table = read_table(src, &KeyPath::parse("a").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn handles_quotes_before_closing_delimiters() {
+        let src = "s = \"\"\"a\"\"\"\"\"\n[a]\nx = 1\n";
+        assert!(check(src, "a"));
+        let src = "k = \"esc \\\" [b]\"\n[b]\nx = 1\n";
+        assert!(check(src, "b"));
+    }
+
+    #[test]
+    fn rejects_non_tables() {
+        let src = "[a]\nx = 1\n[[p]]\n";
+        for path in ["a.x", "p"] {
+            let err = read_table(src, &KeyPath::parse(path).unwrap()).unwrap_err();
+            assert!(matches!(err, Error::TypeMismatch { .. }), "{path}");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Statistics about the contents of a document.
+
+use crate::limits::value_size;
+use crate::path::KeyPath;
+use crate::walk::{is_metadata, walk, Node};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use toml_edit::{DocumentMut, Table};
+
+/// How many of the largest values [`DocStats::largest`] lists.
+pub const LARGEST: usize = 10;
+
+/// Counts and extremes gathered from one walk over a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocStats {
+    /// Size of the document as TOML, in bytes.
+    pub size: usize,
+    /// Standard tables and elements of arrays of tables, not counting the root.
+    pub tables: usize,
+    /// Number of keys directly in each table, starting with the root, in document order.
+    pub keys_per_table: Vec<(KeyPath, usize)>,
+    /// Number of values of each type, including array elements and inline table entries.
+    pub types: BTreeMap<&'static str, usize>,
+    /// Number of keys and indices on the longest path.
+    pub max_depth: usize,
+    /// The first path of length `max_depth`.
+    pub deepest: KeyPath,
+    /// The largest values stored under table keys and their sizes in bytes of TOML, largest
+    /// first.
+    pub largest: Vec<(KeyPath, usize)>,
+}
+
+/// Gathers statistics about `table` and everything below it.
+///
+/// Metadata, as told by [`is_metadata`], is left out of everything but the size.
+pub fn stats(table: &Table) -> DocStats {
+    let mut stats = DocStats {
+        size: DocumentMut::from(table.clone()).to_string().len(),
+        keys_per_table: vec![(KeyPath::new(), data_keys(table, &KeyPath::new()))],
+        ..DocStats::default()
+    };
+    // Path of the table key value currently being walked, so its elements are not ranked.
+    let mut in_value: Option<KeyPath> = None;
+    for entry in walk(table).filter(|entry| !is_metadata(&entry.path)) {
+        if entry.path.len() > stats.max_depth {
+            stats.max_depth = entry.path.len();
+            stats.deepest = entry.path.clone();
+        }
+        match entry.node {
+            Node::Table(table) => {
+                stats.tables += 1;
+                let keys = data_keys(table, &entry.path);
+                stats.keys_per_table.push((entry.path.clone(), keys));
+            }
+            Node::ArrayOfTables(_) => {}
+            Node::Value(value) => {
+                *stats.types.entry(value.type_name()).or_default() += 1;
+                let nested = in_value
+                    .as_ref()
+                    .is_some_and(|outer| entry.path.segments().starts_with(outer.segments()));
+                if !nested {
+                    stats.largest.push((entry.path.clone(), value_size(value)));
+                    in_value = Some(entry.path);
+                }
+            }
+        }
+    }
+    stats.largest.sort_by_key(|(_, size)| Reverse(*size));
+    stats.largest.truncate(LARGEST);
+    stats
+}
+
+/// Number of keys of `table`, at `path`, that are not metadata.
+fn data_keys(table: &Table, path: &KeyPath) -> usize {
+    table
+        .iter()
+        .filter(|(key, _)| !is_metadata(&path.with_key(*key)))
+        .count()
+}
@@ -0,0 +1,131 @@
+//! Rendering text templates from document values.
+//!
+//! A template is any text with placeholders of the form `{{ path }}` or `{{ path | filter }}`,
+//! where `path` is a key path into the document. Without a filter, scalar values are written as
+//! plain text: strings without quotes, numbers and booleans as written in TOML. Filters are:
+//!
+//! - `toml`: the value as TOML, so strings keep their quotes and arrays can be used;
+//! - `json`: the value or table as compact JSON;
+//! - `shell`: the scalar quoted for a POSIX shell.
+//!
+//! There is no escape for a literal `{{`.
+
+use crate::interop::item_to_json;
+use crate::path::KeyPath;
+use crate::walk::{get, Node};
+use crate::{Error, Result};
+use toml_edit::{Item, Table, Value};
+
+/// Renders `template`, replacing each placeholder with the value at its path in `doc`.
+///
+/// Fails with [`Error::MissingKey`] for a path that does not exist, and with
+/// [`Error::TypeMismatch`] for a table or array written without the `toml` or `json` filter.
+pub fn render(template: &str, doc: &Table) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let offset = template.len() - rest.len() + start;
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find("}}") else {
+            return Err(invalid(offset, "unterminated placeholder"));
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        out.push_str(&placeholder(inner, doc, offset)?);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn placeholder(inner: &str, doc: &Table, offset: usize) -> Result<String> {
+    let (path, filter) = match inner.split_once('|') {
+        Some((path, filter)) => (path.trim(), Some(filter.trim())),
+        None => (inner.trim(), None),
+    };
+    if path.is_empty() {
+        return Err(invalid(offset, "placeholder has no key path"));
+    }
+    let path = KeyPath::parse(path)?;
+    let node = get(doc, &path).ok_or_else(|| Error::MissingKey { path: path.clone() })?;
+    match filter {
+        None => scalar_of(node, &path),
+        Some("toml") => match node {
+            Node::Value(value) => {
+                let mut value = value.clone();
+                value.decor_mut().clear();
+                Ok(value.to_string())
+            }
+            node => Err(Error::TypeMismatch {
+                path,
+                expected: "a value",
+                found: node.type_name(),
+            }),
+        },
+        Some("json") => {
+            let item = match node {
+                Node::Table(table) => Item::Table(table.clone()),
+                Node::ArrayOfTables(array) => Item::ArrayOfTables(array.clone()),
+                Node::Value(value) => Item::Value(value.clone()),
+            };
+            Ok(item_to_json(&item)?.to_string())
+        }
+        Some("shell") => Ok(shell_quote(&scalar_of(node, &path)?)),
+        Some(_) => Err(invalid(offset, "unknown filter")),
+    }
+}
+
+fn scalar_of(node: Node<'_>, path: &KeyPath) -> Result<String> {
+    let scalar = match node {
+        Node::Value(value) => value.render_scalar(),
+        _ => None,
+    };
+    scalar.ok_or_else(|| Error::TypeMismatch {
+        path: path.clone(),
+        expected: "a scalar value",
+        found: node.type_name(),
+    })
+}
+
+/// Plain-text rendering of scalar values, as wanted by shell scripts and templates.
+pub trait RenderScalar {
+    /// Returns the content of a scalar without TOML syntax or decor: strings unquoted and
+    /// unescaped, numbers in decimal, `true` or `false`, and datetimes as written. Floats that
+    /// are not finite are `nan`, `inf` or `-inf`. Returns `None` for anything but a scalar.
+    fn render_scalar(&self) -> Option<String>;
+}
+
+impl RenderScalar for Value {
+    fn render_scalar(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.value().clone()),
+            Value::Integer(i) => Some(i.value().to_string()),
+            Value::Float(f) => {
+                let f = *f.value();
+                Some(match f {
+                    f if f.is_nan() => "nan".to_string(),
+                    f if f.is_infinite() && f > 0.0 => "inf".to_string(),
+                    f if f.is_infinite() => "-inf".to_string(),
+                    f => format!("{f:?}"),
+                })
+            }
+            Value::Boolean(b) => Some(b.value().to_string()),
+            Value::Datetime(dt) => Some(dt.value().to_string()),
+            Value::Array(_) | Value::InlineTable(_) => None,
+        }
+    }
+}
+
+impl RenderScalar for Item {
+    fn render_scalar(&self) -> Option<String> {
+        self.as_value().and_then(Value::render_scalar)
+    }
+}
+
+/// Quotes `s` as a single word for a POSIX shell.
+pub(crate) fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn invalid(offset: usize, reason: &'static str) -> Error {
+    Error::InvalidTemplate { offset, reason }
+}
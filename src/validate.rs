@@ -0,0 +1,180 @@
+//! Validation of TOML documents before they are used as a database.
+
+use crate::constraints::{Constraints, Violation};
+use crate::path::KeyPath;
+use crate::walk::{walk, Node};
+use crate::Error;
+use std::fmt;
+use toml_edit::{DocumentMut, TomlError, Value};
+
+/// A problem found while validating a document.
+#[derive(Debug)]
+pub enum Problem {
+    /// The input is not valid UTF-8, starting at the given byte offset.
+    InvalidUtf8 { offset: usize },
+    /// The input is not a valid TOML document. Duplicate keys and tables are reported here.
+    Parse(TomlError),
+    /// An array holds values of more than one type.
+    MixedArray {
+        path: KeyPath,
+        types: Vec<&'static str>,
+    },
+    /// The constraints stored in the document cannot be read.
+    Constraints(Error),
+    /// A value does not satisfy a constraint or a custom validator.
+    Violation(Violation),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::InvalidUtf8 { offset } => write!(f, "invalid UTF-8 at byte {offset}"),
+            Problem::Parse(err) => write!(f, "{err}"),
+            Problem::MixedArray { path, types } => {
+                write!(f, "array `{path}` mixes {}", types.join(", "))
+            }
+            Problem::Constraints(err) => write!(f, "{err}"),
+            Problem::Violation(violation) => write!(f, "{violation}"),
+        }
+    }
+}
+
+/// A check run against a parsed document on top of the structural ones.
+///
+/// [`Constraints`] are validators, as is any `Fn(&DocumentMut) -> Vec<Violation>`.
+pub trait Validator {
+    /// Returns every violation found in `doc`.
+    fn check(&self, doc: &DocumentMut) -> Vec<Violation>;
+}
+
+impl Validator for Constraints {
+    fn check(&self, doc: &DocumentMut) -> Vec<Violation> {
+        Constraints::check(self, doc)
+    }
+}
+
+impl<F: Fn(&DocumentMut) -> Vec<Violation>> Validator for F {
+    fn check(&self, doc: &DocumentMut) -> Vec<Violation> {
+        self(doc)
+    }
+}
+
+/// Validates the raw bytes of a document, returning every problem found.
+///
+/// An empty result means the document is safe to open. Structural checks and the constraints
+/// stored in the document only run once the input has been decoded and parsed.
+pub fn validate(bytes: &[u8]) -> Vec<Problem> {
+    validate_with(bytes, &[])
+}
+
+/// Validates the raw bytes of a document like [`validate`], also running `validators`.
+pub fn validate_with(bytes: &[u8], validators: &[&dyn Validator]) -> Vec<Problem> {
+    let src = match std::str::from_utf8(bytes) {
+        Ok(src) => src,
+        Err(err) => {
+            return vec![Problem::InvalidUtf8 {
+                offset: err.valid_up_to(),
+            }]
+        }
+    };
+    match src.parse::<DocumentMut>() {
+        Ok(doc) => validate_document_with(&doc, validators),
+        Err(err) => vec![Problem::Parse(err)],
+    }
+}
+
+/// Runs the structural checks and stored constraints against an already parsed document.
+pub fn validate_document(doc: &DocumentMut) -> Vec<Problem> {
+    validate_document_with(doc, &[])
+}
+
+/// Validates an already parsed document like [`validate_document`], also running `validators`.
+pub fn validate_document_with(doc: &DocumentMut, validators: &[&dyn Validator]) -> Vec<Problem> {
+    let mut problems = vec![];
+    for entry in walk(doc) {
+        let Node::Value(Value::Array(array)) = entry.node else {
+            continue;
+        };
+        let mut types: Vec<&'static str> = vec![];
+        for value in array.iter() {
+            if !types.contains(&value.type_name()) {
+                types.push(value.type_name());
+            }
+        }
+        if types.len() > 1 {
+            problems.push(Problem::MixedArray {
+                path: entry.path,
+                types,
+            });
+        }
+    }
+    match Constraints::from_document(doc) {
+        Ok(constraints) => {
+            let violations = constraints.check(doc);
+            problems.extend(violations.into_iter().map(Problem::Violation));
+        }
+        Err(err) => problems.push(Problem::Constraints(err)),
+    }
+    for validator in validators {
+        let violations = validator.check(doc);
+        problems.extend(violations.into_iter().map(Problem::Violation));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::{Constraint, Kind};
+
+    #[test]
+    fn reports_structural_problems() {
+        assert!(validate(b"a = [1, 2]\n").is_empty());
+        assert!(matches!(
+            validate(b"a = 1\xff\n")[..],
+            [Problem::InvalidUtf8 { offset: 5 }]
+        ));
+        assert!(matches!(
+            validate(b"a = 1\na = 2\n")[..],
+            [Problem::Parse(_)]
+        ));
+        let problems = validate(b"[t]\na = [1, \"x\"]\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].to_string(), "array `t.a` mixes integer, string");
+    }
+
+    #[test]
+    fn checks_stored_constraints() {
+        let src = "port = 0\n[__constraints]\nport = { type = \"integer\", min = 1 }\n";
+        let problems = validate(src.as_bytes());
+        assert!(matches!(&problems[..], [Problem::Violation(v)] if v.path.to_string() == "port"));
+        let problems = validate(b"[__constraints]\nport = { type = \"nope\" }\n");
+        assert!(matches!(problems[..], [Problem::Constraints(_)]));
+    }
+
+    #[test]
+    fn runs_validators() {
+        let mut constraints = Constraints::new();
+        let required = Constraint {
+            kind: Some(Kind::String),
+            required: true,
+            ..Constraint::default()
+        };
+        constraints.add(KeyPath::parse("host").unwrap(), required);
+        let no_debug = |doc: &DocumentMut| {
+            doc.contains_key("debug")
+                .then(|| Violation {
+                    path: KeyPath::parse("debug").unwrap(),
+                    message: "must not be set in production".to_string(),
+                })
+                .into_iter()
+                .collect()
+        };
+        let problems = validate_with(b"debug = true\n", &[&constraints, &no_debug]);
+        let messages: Vec<String> = problems.iter().map(ToString::to_string).collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0], "`host` is required");
+        assert_eq!(messages[1], "`debug` must not be set in production");
+        assert!(validate_with(b"host = \"db\"\n", &[&constraints, &no_debug]).is_empty());
+    }
+}
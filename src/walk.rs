@@ -0,0 +1,334 @@
+//! Depth-first traversal of TOML documents.
+//!
+//! [`walk`] and [`walk_mut`] visit tables, arrays of tables, arrays and inline tables alike, and
+//! are what searching, linting and validation are built on.
+
+use crate::path::{KeyPath, Segment};
+use crate::{alias, constraints, deprecation, migrations};
+use toml_edit::{ArrayOfTables, Item, Table, Value};
+
+/// A part of the document reached by [`walk`].
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Table(&'a Table),
+    ArrayOfTables(&'a ArrayOfTables),
+    Value(&'a Value),
+}
+
+impl<'a> Node<'a> {
+    /// Returns the node for an item, or `None` for [`Item::None`].
+    pub fn from_item(item: &'a Item) -> Option<Node<'a>> {
+        match item {
+            Item::Value(value) => Some(Node::Value(value)),
+            Item::Table(table) => Some(Node::Table(table)),
+            Item::ArrayOfTables(array) => Some(Node::ArrayOfTables(array)),
+            Item::None => None,
+        }
+    }
+
+    /// Returns the node at `path` below this one, or `None` if the path does not lead anywhere.
+    pub fn get(self, path: &KeyPath) -> Option<Node<'a>> {
+        path.segments()
+            .iter()
+            .try_fold(self, |node, segment| node.child(segment))
+    }
+
+    pub(crate) fn child(self, segment: &Segment) -> Option<Node<'a>> {
+        let child = match (self, segment) {
+            (Node::Table(table), Segment::Key(key)) => Node::from_item(table.get(key)?)?,
+            (Node::Value(Value::InlineTable(table)), Segment::Key(key)) => {
+                Node::Value(table.get(key)?)
+            }
+            (Node::Value(Value::Array(array)), Segment::Index(i)) => Node::Value(array.get(*i)?),
+            (Node::ArrayOfTables(array), Segment::Index(i)) => Node::Table(array.get(*i)?),
+            _ => return None,
+        };
+        Some(child)
+    }
+
+    /// Returns the TOML type name of the node.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Node::Table(_) => "table",
+            Node::ArrayOfTables(_) => "array of tables",
+            Node::Value(value) => value.type_name(),
+        }
+    }
+}
+
+/// A mutable part of the document reached by [`walk_mut`].
+#[derive(Debug)]
+pub enum NodeMut<'a> {
+    Table(&'a mut Table),
+    ArrayOfTables(&'a mut ArrayOfTables),
+    Value(&'a mut Value),
+}
+
+impl NodeMut<'_> {
+    /// Returns the TOML type name of the node.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NodeMut::Table(_) => "table",
+            NodeMut::ArrayOfTables(_) => "array of tables",
+            NodeMut::Value(value) => value.type_name(),
+        }
+    }
+}
+
+/// A node together with where it was found.
+#[derive(Debug, Clone)]
+pub struct Entry<'a> {
+    /// Path of the node, relative to the table the walk started from.
+    pub path: KeyPath,
+    /// The node's own key, or `None` for elements of arrays and arrays of tables.
+    pub key: Option<&'a str>,
+    pub node: Node<'a>,
+}
+
+/// Walks every node below `table` in depth-first, document order.
+///
+/// Each container is yielded before its children. The starting table itself is not yielded.
+pub fn walk(table: &Table) -> Walk<'_> {
+    Walk {
+        stack: vec![Frame::Table(KeyPath::new(), table.iter())],
+    }
+}
+
+/// Returns the node at `path` below `table`, or `None` if the path does not lead anywhere.
+pub fn get<'a>(table: &'a Table, path: &KeyPath) -> Option<Node<'a>> {
+    Node::Table(table).get(path)
+}
+
+/// Returns the node at `path` below `table`, or `default` if the path does not lead anywhere.
+///
+/// The default is only returned, never written; see [`set_default`] for that.
+///
+/// [`set_default`]: crate::ops::set_default
+pub fn get_or<'a>(table: &'a Table, path: &KeyPath, default: &'a Value) -> Node<'a> {
+    get(table, path).unwrap_or(Node::Value(default))
+}
+
+/// Returns the TOML type name of the node at `path` below `table`, as given by
+/// [`Node::type_name`], or `None` if the path does not lead anywhere.
+pub fn type_of(table: &Table, path: &KeyPath) -> Option<&'static str> {
+    get(table, path).map(|node| node.type_name())
+}
+
+/// Returns whether `path` is, or lies inside, metadata the crate keeps in documents: the
+/// [`constraints`], [`deprecation`] and [`migrations`] tables at the root, and [`alias`] keys at
+/// any depth.
+///
+/// Consumers that treat the document as plain data, such as linting, exports, statistics and
+/// drift, skip these paths.
+///
+/// [`alias`]: crate::alias
+/// [`constraints`]: crate::constraints
+/// [`deprecation`]: crate::deprecation
+/// [`migrations`]: crate::migrations
+pub fn is_metadata(path: &KeyPath) -> bool {
+    let root = [constraints::TABLE, deprecation::TABLE, migrations::KEY];
+    let at_root =
+        matches!(path.segments().first(), Some(Segment::Key(key)) if root.contains(&key.as_str()));
+    at_root
+        || path
+            .segments()
+            .iter()
+            .any(|segment| matches!(segment, Segment::Key(key) if key == alias::ALIAS_KEY))
+}
+
+/// Returns the node at `path` below `table` like [`get`], but matching keys the way people tend
+/// to misspell them: ignoring case and treating `-` and `_` as the same.
+///
+/// An exact match is always preferred. A key that matches several keys only after normalizing
+/// matches none of them. Returns the path of the node as written in the document, along with the
+/// node.
+pub fn get_normalized<'a>(table: &'a Table, path: &KeyPath) -> Option<(KeyPath, Node<'a>)> {
+    let mut node = Node::Table(table);
+    let mut found = KeyPath::new();
+    for segment in path.segments() {
+        let segment = match (node, segment) {
+            (Node::Table(table), Segment::Key(key)) => {
+                Segment::Key(match_key(table.iter().map(|(k, _)| k), key)?.to_string())
+            }
+            (Node::Value(Value::InlineTable(table)), Segment::Key(key)) => {
+                Segment::Key(match_key(table.iter().map(|(k, _)| k), key)?.to_string())
+            }
+            (_, segment) => segment.clone(),
+        };
+        node = node.child(&segment)?;
+        found.push(segment);
+    }
+    Some((found, node))
+}
+
+/// Normalizes a key for [`get_normalized`]: lower-cased, with `-` replaced by `_`.
+pub fn normalize_key(key: &str) -> String {
+    key.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == '-' { '_' } else { c })
+        .collect()
+}
+
+fn match_key<'a>(keys: impl Iterator<Item = &'a str>, key: &str) -> Option<&'a str> {
+    let wanted = normalize_key(key);
+    let mut matches = vec![];
+    for candidate in keys {
+        if candidate == key {
+            return Some(candidate);
+        }
+        if normalize_key(candidate) == wanted {
+            matches.push(candidate);
+        }
+    }
+    match matches[..] {
+        [only] => Some(only),
+        _ => None,
+    }
+}
+
+/// Returns the node at `path` below `table` for modification, or `None` if the path does not
+/// lead anywhere.
+pub fn get_mut<'a>(table: &'a mut Table, path: &KeyPath) -> Option<NodeMut<'a>> {
+    let mut node = NodeMut::Table(table);
+    for segment in path.segments() {
+        node = match (node, segment) {
+            (NodeMut::Table(table), Segment::Key(key)) => match table.get_mut(key)? {
+                Item::Value(value) => NodeMut::Value(value),
+                Item::Table(table) => NodeMut::Table(table),
+                Item::ArrayOfTables(array) => NodeMut::ArrayOfTables(array),
+                Item::None => return None,
+            },
+            (NodeMut::Value(Value::InlineTable(table)), Segment::Key(key)) => {
+                NodeMut::Value(table.get_mut(key)?)
+            }
+            (NodeMut::Value(Value::Array(array)), Segment::Index(i)) => {
+                NodeMut::Value(array.get_mut(*i)?)
+            }
+            (NodeMut::ArrayOfTables(array), Segment::Index(i)) => {
+                NodeMut::Table(array.get_mut(*i)?)
+            }
+            _ => return None,
+        };
+    }
+    Some(node)
+}
+
+/// Calls `f` on every node below `table`, in the same order as [`walk`].
+///
+/// A container is visited before its children, so changes `f` makes to it are seen when its
+/// children are visited.
+pub fn walk_mut(table: &mut Table, mut f: impl FnMut(&KeyPath, NodeMut<'_>)) {
+    walk_table_mut(table, &KeyPath::new(), &mut f);
+}
+
+/// Iterator returned by [`walk`].
+pub struct Walk<'a> {
+    stack: Vec<Frame<'a>>,
+}
+
+enum Frame<'a> {
+    Table(KeyPath, toml_edit::Iter<'a>),
+    ArrayOfTables(
+        KeyPath,
+        std::iter::Enumerate<toml_edit::ArrayOfTablesIter<'a>>,
+    ),
+    Array(KeyPath, std::iter::Enumerate<toml_edit::ArrayIter<'a>>),
+    InlineTable(KeyPath, toml_edit::InlineTableIter<'a>),
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.stack.last_mut()? {
+                Frame::Table(path, iter) => match iter.next() {
+                    Some((key, item)) => {
+                        let Some(node) = Node::from_item(item) else {
+                            continue;
+                        };
+                        Some(Entry {
+                            path: path.with_key(key),
+                            key: Some(key),
+                            node,
+                        })
+                    }
+                    None => None,
+                },
+                Frame::ArrayOfTables(path, iter) => iter.next().map(|(i, table)| Entry {
+                    path: path.with_index(i),
+                    key: None,
+                    node: Node::Table(table),
+                }),
+                Frame::Array(path, iter) => iter.next().map(|(i, value)| Entry {
+                    path: path.with_index(i),
+                    key: None,
+                    node: Node::Value(value),
+                }),
+                Frame::InlineTable(path, iter) => iter.next().map(|(key, value)| Entry {
+                    path: path.with_key(key),
+                    key: Some(key),
+                    node: Node::Value(value),
+                }),
+            };
+            let Some(entry) = entry else {
+                self.stack.pop();
+                continue;
+            };
+            let path = entry.path.clone();
+            match entry.node {
+                Node::Table(table) => self.stack.push(Frame::Table(path, table.iter())),
+                Node::ArrayOfTables(array) => self
+                    .stack
+                    .push(Frame::ArrayOfTables(path, array.iter().enumerate())),
+                Node::Value(Value::Array(array)) => self
+                    .stack
+                    .push(Frame::Array(path, array.iter().enumerate())),
+                Node::Value(Value::InlineTable(table)) => {
+                    self.stack.push(Frame::InlineTable(path, table.iter()))
+                }
+                Node::Value(_) => {}
+            }
+            return Some(entry);
+        }
+    }
+}
+
+fn walk_table_mut(table: &mut Table, path: &KeyPath, f: &mut dyn FnMut(&KeyPath, NodeMut<'_>)) {
+    for (key, item) in table.iter_mut() {
+        let path = path.with_key(key.get());
+        match item {
+            Item::Value(value) => walk_value_mut(value, &path, f),
+            Item::Table(table) => {
+                f(&path, NodeMut::Table(table));
+                walk_table_mut(table, &path, f);
+            }
+            Item::ArrayOfTables(array) => {
+                f(&path, NodeMut::ArrayOfTables(array));
+                for (i, table) in array.iter_mut().enumerate() {
+                    let path = path.with_index(i);
+                    f(&path, NodeMut::Table(table));
+                    walk_table_mut(table, &path, f);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn walk_value_mut(value: &mut Value, path: &KeyPath, f: &mut dyn FnMut(&KeyPath, NodeMut<'_>)) {
+    f(path, NodeMut::Value(value));
+    match value {
+        Value::Array(array) => {
+            for (i, value) in array.iter_mut().enumerate() {
+                walk_value_mut(value, &path.with_index(i), f);
+            }
+        }
+        Value::InlineTable(table) => {
+            for (key, value) in table.iter_mut() {
+                walk_value_mut(value, &path.with_key(key.get()), f);
+            }
+        }
+        _ => {}
+    }
+}